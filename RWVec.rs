@@ -2,13 +2,63 @@
 #![crate_type = "rlib"]
 #![crate_type = "dylib"]
 
-#![feature(unsafe_destructor)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
-use std::cell::UnsafeCell;
-use std::sync::{ StaticRwLock, StaticMutex, RW_LOCK_INIT, MUTEX_INIT, Arc };
-use std::marker::Sync;
+//house style clippy doesn't share: field : field initializers, lifetimes spelled out
+//on deref, raw offsets and nested ifs that read better apart
+#![allow(clippy::redundant_field_names, clippy::needless_lifetimes, clippy::ptr_offset_with_cast)]
+#![allow(clippy::collapsible_if, clippy::collapsible_else_if, clippy::collapsible_match)]
+#![allow(clippy::type_complexity)]
+#![allow(clippy::new_without_default, clippy::len_without_is_empty, clippy::declare_interior_mutable_const)]
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "futures-core")]
+extern crate futures_core;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(all(target_os = "linux", feature = "huge-pages"))]
+extern crate libc;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+#[cfg(any(feature = "serde", feature = "json", feature = "csv"))]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate bincode;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+#[cfg(feature = "flatbuffers")]
+extern crate flatbuffers;
+#[cfg(feature = "arrow")]
+extern crate arrow;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "csv")]
+extern crate csv;
+#[cfg(feature = "metrics")]
+#[macro_use]
+extern crate metrics;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+use std::cell::{ Cell, UnsafeCell };
+use std::sync::{ Arc, Mutex, Condvar, mpsc };
+use std::sync::atomic::{ AtomicUsize, AtomicBool, AtomicPtr, Ordering };
+use std::marker::{ Sync, PhantomData };
 use std::iter::IntoIterator;
-use std::ops::{ Deref, DerefMut, Drop };
+use std::ops::{ Deref, DerefMut, Drop, RangeBounds };
+use std::{ fmt, io, mem, ptr };
+use std::time::{ Duration, Instant };
+use std::task::{ Context, Poll, Waker };
+use std::future::Future;
+use std::pin::Pin;
+use std::collections::TryReserveError;
+#[cfg(feature = "watchdog")]
+use std::collections::HashMap;
+#[cfg(any(feature = "watchdog", feature = "debug-locks"))]
+use std::panic::Location;
+use std::collections::VecDeque;
 
 ///////////////////////////////////////////////////////////////////////////////
 //                                                                           //
@@ -16,331 +66,7632 @@ use std::ops::{ Deref, DerefMut, Drop };
 //                                                                           //
 ///////////////////////////////////////////////////////////////////////////////
 
-struct RWVec<T> {
-    rw_lock   : Box<StaticRwLock>,
-    push_lock : Box<StaticMutex>,
-    data      : UnsafeCell<std::vec::Vec<T>>
+pub struct RWVec<T> {
+    //shows up in panics and diagnostics so you can tell the vecs apart
+    name           : Option<String>,
+    rw_lock        : ResizeLock,
+    push_lock      : CachePadded<PushLock>,
+    data           : UnsafeCell<std::vec::Vec<T>>,
+    //fraction of the capacity at which the next buffer gets prepared ahead of time
+    preallocate_at : Option<f32>,
+    //only one thread prepares the next buffer at a time
+    preallocating  : AtomicBool,
+    //gives memory back once the vec has been mostly empty for a while
+    shrinker       : Shrinker,
+    //zero sized elements don't need a buffer, the len is all there is to them
+    zst_len        : CachePadded<AtomicUsize>,
+    //which readers are still around from before a given point in time
+    epochs         : ReaderEpochs,
+    //every push bumps this...together with the write lock generation it makes the version
+    pushes         : CachePadded<AtomicUsize>,
+    //woken whenever the version moves, for anyone blocked waiting on the contents
+    changed        : Signal,
+    //the most elements the vec holds and what a push does once it's there
+    bound          : Option<(usize, OverflowPolicy)>,
+    //slots of a bounded vec set aside for permits that haven't pushed yet
+    reserved       : AtomicUsize,
+    //tells someone when the vec is getting full and when it has emptied out again
+    watermarks     : Option<Watermarks>,
+    //the capacity clear and drain shrink down to (and take_all leaves behind)
+    drain_floor    : Option<usize>,
+    //the most bytes the elements may add up to before the oldest get evicted
+    budget         : Option<Budget<T>>,
+    //ask for the buffer to be backed with huge pages whenever it moves
+    #[cfg(feature = "huge-pages")]
+    huge_pages     : bool,
+    //keeps an eye on guards that are held for too long
+    #[cfg(feature = "watchdog")]
+    watchdog       : Option<Arc<Watchdog>>,
+    //how long waiting for or holding a lock may take before it gets logged
+    #[cfg(feature = "log")]
+    slow_locks     : Option<SlowLocks>,
+    //set when a writer or exclusive access is dropped by a panicking thread
+    poisoned       : AtomicBool,
+    //how often the expensive paths get taken, for stats()
+    counters       : Counters,
+    //everyone who wants to hear about changes...subscribed saves the lock when nobody does
+    subscribers    : Mutex<std::vec::Vec<mpsc::Sender<ChangeEvent>>>,
+    subscribed     : AtomicBool,
+    //one per broadcast receiver, each clones pushed elements into its queue...false
+    //once the receiver is gone. broadcasting saves the lock when there are none
+    sinks          : Mutex<std::vec::Vec<Box<dyn Fn(&T) -> bool + Send>>>,
+    broadcasting   : AtomicBool,
+    //the latest structural changes, for events()
+    #[cfg(feature = "debug-events")]
+    event_log      : EventLog,
+    //who holds the write lock and who read last, for dump()
+    #[cfg(feature = "debug-locks")]
+    lock_log       : LockLog
+}
+
+//a copy of the counters at one point in time...pushes that often realloc want a
+//bigger starting capacity or preallocate_at, pushes that often find the push lock
+//taken want fewer pushers or extend_from_slice
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Stats {
+    //elements pushed, counting every element of a batch
+    pub pushes           : usize,
+    //pushes (or batches) that had to move the buffer with every reader locked out
+    pub realloc_pushes   : usize,
+    //pushes (or batches) that found another pusher holding the push lock
+    pub contended_pushes : usize,
+    pub refreshes        : usize,
+    pub upgrades         : usize
+}
+
+struct Counters {
+    realloc_pushes   : AtomicUsize,
+    contended_pushes : AtomicUsize,
+    refreshes        : AtomicUsize,
+    upgrades         : AtomicUsize
+}
+
+impl Counters {
+    fn new() -> Counters {
+        Counters {
+            realloc_pushes   : AtomicUsize::new(0),
+            contended_pushes : AtomicUsize::new(0),
+            refreshes        : AtomicUsize::new(0),
+            upgrades         : AtomicUsize::new(0)
+        }
+    }
+}
+
+//what subscribers get told after each change to the contents
+#[derive(Clone, PartialEq, Debug)]
+pub enum ChangeEvent {
+    //elements landed at these indices
+    Appended  { range : std::ops::Range<usize> },
+    //everything from len on is gone
+    Truncated { len : usize },
+    //the elements at these indices may have been swapped out or written to
+    Replaced  { range : std::ops::Range<usize> },
+    //everything is gone
+    Cleared
+}
+
+//how a push goes about the limit of a bounded vec
+#[derive(Clone, Copy, PartialEq)]
+enum Admission {
+    //whatever the vec's overflow policy says
+    Policy,
+    //turned away instead of blocking
+    NoWait,
+    //fills a slot set aside by a Permit, so it never finds the vec full
+    Reserved
+}
+
+//what a push does when a bounded vec is full
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OverflowPolicy {
+    //wait until something gets taken out
+    Block,
+    //hand the element back from try_push...push panics
+    Reject,
+    //throw out the element at the front to make room
+    DropOldest,
+    //throw out the element being pushed
+    DropNewest
 }
 
+//why one of the try_ methods didn't do what it was asked
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RwVecError {
+    //the lock is held and the call wasn't allowed to wait
+    WouldBlock,
+    //the lock was still held when the timeout ran out
+    TimedOut,
+    //a thread panicked while writing...see clear_poison
+    Poisoned,
+    //a bounded vec had no room
+    Full,
+    //growing the buffer failed
+    AllocError
+}
+
+impl fmt::Display for RwVecError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            RwVecError::WouldBlock => "the lock is held elsewhere",
+            RwVecError::TimedOut   => "timed out waiting for the lock",
+            RwVecError::Poisoned   => "poisoned by a panic while it was being written to",
+            RwVecError::Full       => "the vec is full",
+            RwVecError::AllocError => "allocation failed"
+        })
+    }
+}
+
+impl std::error::Error for RwVecError { }
+
 unsafe impl<T : Send> Sync for RWVec<T> { }
 
+//notes a structural change in the vec's event log...compiles to nothing without the
+//debug-events feature
+macro_rules! log_event {
+    ($vec:expr, $event:expr) => {
+        #[cfg(feature = "debug-events")]
+        $vec.event_log.record($event);
+    }
+}
+
+//vec! for RWVecs: rwvec![a, b, c] or rwvec![x; n], giving the usual Arc<RWVec<T>>
+#[macro_export]
+macro_rules! rwvec {
+    () => {
+        $crate::RWVec::new()
+    };
+    ($elem:expr; $n:expr) => {
+        $crate::RWVec::from_vec(vec![$elem; $n])
+    };
+    ($($x:expr),+ $(,)*) => {
+        $crate::RWVec::from_vec(vec![$($x),+])
+    };
+}
+
 impl<T> RWVec<T> {
     pub fn new() -> Arc<RWVec<T>> {
-        Arc::new(RWVec {  
-            rw_lock   : Box::new(RW_LOCK_INIT),
-            push_lock : Box::new(MUTEX_INIT),
-            data      : UnsafeCell::new(std::vec::Vec::new())
-        })
+        RWVec::builder().build()
     }
 
     pub fn with_capacity(capacity : usize) -> Arc<RWVec<T>> {
-        Arc::new(RWVec {  
-            rw_lock   : Box::new(RW_LOCK_INIT),
-            push_lock : Box::new(MUTEX_INIT),
-            data      : UnsafeCell::new(std::vec::Vec::with_capacity(capacity))
-        })
+        RWVec::builder().capacity(capacity).build()
     }
 
-    pub fn push(&mut self, t : T) {
-        //compete with other pushers
-        unsafe { self.push_lock.lock.lock(); }
-        
-        //the push will cause a realloc
-        if self.data.value.capacity() == self.data.value.len() {
-            //compete with other pushers and all the readers as well
-            unsafe { self.rw_lock.lock.write(); }
-            //push reallocs underlying mem and copys over old values
-            self.data.value.push(t);
+    //takes over vec's elements and buffer as they are, nothing gets copied
+    pub fn from_vec(vec : std::vec::Vec<T>) -> Arc<RWVec<T>> {
+        let mut built = RWVec::from_builder(RWVec::builder());
+        *built.get_mut() = vec;
+        Arc::new(built)
+    }
 
-            unsafe { 
-                //safe to read
-                self.rw_lock.lock.write_unlock();
-                //safe to push again
-                self.push_lock.lock.unlock();
-            }
+    pub fn builder() -> Builder<T> {
+        Builder::new()
+    }
 
-            return
+    fn from_builder(builder : Builder<T>) -> RWVec<T> {
+        //the closures below can't reach into builder while its other fields move out
+        #[cfg(feature = "watchdog")]
+        let name = builder.name.clone();
+        #[cfg(feature = "log")]
+        let label = match builder.name {
+            Some(ref name) => format!("RWVec '{}'", name),
+            None           => "RWVec".to_string()
+        };
+
+        let vec = RWVec {
+            name           : builder.name.clone(),
+            rw_lock        : ResizeLock::new(builder.lock_backend),
+            push_lock      : CachePadded::new(PushLock::new()),
+            data           : UnsafeCell::new(std::vec::Vec::with_capacity(builder.capacity)),
+            preallocate_at : builder.preallocate_at,
+            preallocating  : AtomicBool::new(false),
+            shrinker       : Shrinker::new(builder.shrink_policy),
+            zst_len        : CachePadded::new(AtomicUsize::new(0)),
+            epochs         : ReaderEpochs::new(),
+            pushes         : CachePadded::new(AtomicUsize::new(0)),
+            changed        : Signal::new(),
+            bound          : builder.bound,
+            reserved       : AtomicUsize::new(0),
+            watermarks     : builder.watermarks,
+            drain_floor    : builder.drain_floor,
+            //zero sized elements have nothing to weigh
+            budget         : builder.budget.filter(|_| !RWVec::<T>::is_zst()).map(|(limit, size_of)| Budget {
+                limit   : limit,
+                size_of : size_of,
+                used    : AtomicUsize::new(0)
+            }),
+            #[cfg(feature = "huge-pages")]
+            huge_pages     : builder.huge_pages,
+            #[cfg(feature = "watchdog")]
+            watchdog       : builder.watchdog.map(move |(limit, callback)| Watchdog::spawn(name, limit, callback)),
+            #[cfg(feature = "log")]
+            slow_locks     : builder.slow_locks.map(move |(acquire, hold)| SlowLocks {
+                label   : label,
+                acquire : acquire,
+                hold    : hold
+            }),
+            poisoned       : AtomicBool::new(false),
+            counters       : Counters::new(),
+            subscribers    : Mutex::new(std::vec::Vec::new()),
+            subscribed     : AtomicBool::new(false),
+            sinks          : Mutex::new(std::vec::Vec::new()),
+            broadcasting   : AtomicBool::new(false),
+            #[cfg(feature = "debug-events")]
+            event_log      : EventLog::new(),
+            #[cfg(feature = "debug-locks")]
+            lock_log       : LockLog::new()
+        };
+
+        vec.advise(unsafe { vec.vec() });
+        vec
+    }
+
+    //a full vec that drops the newest element just drops t, one that rejects pushes
+    //panics, and so does a failed realloc...use try_push to get t back instead
+    pub fn push(&self, t : T) {
+        let drops_newest = self.bound.map(|(_, policy)| policy) == Some(OverflowPolicy::DropNewest);
+
+        match self.push_then(move |_| t, |_, index| index, Admission::Policy) {
+            Ok((_, preallocate)) => {
+                if preallocate {
+                    self.preallocate();
+                }
+            },
+            //t goes down with the closure
+            Err((RwVecError::Full, _)) if drops_newest => (),
+            Err((error, _))                            => self.push_failed(error)
         }
-        
-        //push that doesnt affect reads
-        (&mut *self.data.get()).push(t);
-        //safe to push again
-        unsafe { self.push_lock.lock.unlock(); }
     }
 
-    pub fn reader(&self) -> SliceGuard<T> {
-        //return a view of the current snapshot 
-        SliceGuard::new(&*self.data.get(), &self.rw_lock, &self.push_lock)
+    //push for bounded vecs that reject (or drop) the newest element, or for when the
+    //buffer might not be able to grow...t comes back if the vec is full or the realloc
+    //failed, otherwise this is push_indexed
+    pub fn try_push(&self, t : T) -> Result<usize, T> {
+        match self.push_then(move |_| t, |_, index| index, Admission::Policy) {
+            Ok((index, preallocate)) => {
+                if preallocate {
+                    self.preallocate();
+                }
+
+                Ok(index)
+            },
+            //the element was never made, so making it just hands t back
+            Err((_, make)) => Err(make(0))
+        }
     }
-    
-    pub fn writer(&mut self) -> SliceGuardMut<T> {
-        //return a mutable, upgradable view of the current snapshot 
-        SliceGuardMut::new(&*self.data.get(), &self.rw_lock, &self.push_lock)
+
+    //push that hands back the index the element landed at...it is decided under the
+    //push lock so it's safe to pass around as a handle, unlike reading len() afterwards.
+    //there's no index to hand back for an element that was turned away, so this panics
+    //if the vec is full or can't grow
+    pub fn push_indexed(&self, t : T) -> usize {
+        self.push_with(move |_| t)
     }
-}
 
-#[unsafe_destructor]
-impl<T> Drop for RWVec<T> {
-    fn drop(&mut self) {
-        unsafe { self.rw_lock.lock.destroy() }
-        unsafe { self.push_lock.lock.destroy() }
+    //builds the element inside the push lock, so whatever it derives from its index
+    //(an id, a back reference) is guaranteed to match where it ends up. f should be
+    //quick, every other pusher is waiting on it
+    pub fn push_with<F>(&self, f : F) -> usize where F : FnOnce(usize) -> T {
+        let (index, preallocate) = match self.push_then(f, |_, index| index, Admission::Policy) {
+            Ok(pushed)      => pushed,
+            Err((error, _)) => self.push_failed(error)
+        };
+
+        if preallocate {
+            self.preallocate();
+        }
+
+        index
     }
-}
 
-///////////////////////////////////////////////////////////////////////////////
-//                                                                           //
-//                             IMMUTABLE GUARD                               //                               
-//                                                                           //
-///////////////////////////////////////////////////////////////////////////////
+    //push_with that never blocks or panics...f is only called once there is room for its
+    //element, so nothing is lost if the vec is full or the buffer can't grow
+    pub fn try_push_with<F>(&self, f : F) -> Result<usize, RwVecError> where F : FnOnce(usize) -> T {
+        match self.push_then(f, |_, index| index, Admission::NoWait) {
+            Ok((index, preallocate)) => {
+                if preallocate {
+                    self.preallocate();
+                }
 
-//multiple read access to a slice representing the current
-//state of the Vec...pushers can still push on the vec as long as they don't 
-//need to reallocate
-struct SliceGuard<'locked, T : 'locked> {
-    //the underlying vec
-    vec         : &'locked std::vec::Vec<T>,
-    //how far to slice on deref...pushers may have corrupted past here
-    end         : usize,
-    //unlock on drop
-    resize_lock : &'locked Box<StaticRwLock>,
-    //in case we need to refresh this needs to be accuired
-    push_lock   : &'locked Box<StaticMutex>
-}   
+                Ok(index)
+            },
+            Err((error, _)) => Err(error)
+        }
+    }
 
-impl<'locked, T> SliceGuard<'locked, T> {
-    fn new(vec : &'locked std::vec::Vec<T>, resize_lock :  &'locked Box<StaticRwLock>, push_lock : &'locked Box<StaticMutex>) -> SliceGuard<'locked, T> {
-        unsafe { resize_lock.lock.read() }
+    //pushes t and hands back a read guard on it...the read lock is taken before the
+    //push lock is let go, so no other pusher can move the element in between. any
+    //preallocation would need the write lock this guard is holding off, so it is left
+    //to the next push
+    pub fn push_get(&self, t : T) -> ElementGuard<'_, T> {
+        let pushed = self.push_then(move |_| t, |_, index| {
+            ElementGuard {
+                guard : SliceGuard::new(self),
+                index : index
+            }
+        }, Admission::Policy);
 
-        SliceGuard {
-            vec         : vec,
-            end         : vec.len(),
-            resize_lock : resize_lock,
-            push_lock   : push_lock
-        }   
+        match pushed {
+            Ok((guard, _))  => guard,
+            Err((error, _)) => self.push_failed(error)
+        }
     }
 
-    //this updates your view of the vec by yielding and then acquiring both locks
-    fn refresh(&mut self) { 
-        unsafe {
-            //give the pending reallocating pushers a chance to finish so no deadlock
-            self.resize_lock.lock.read_unlock(); 
-            //seal off the pushers
-            self.push_lock.lock.lock();
-            //register yourself as a reader again
-            self.resize_lock.lock.read(); 
+    //the len may have moved...wakes up anyone waiting on the contents and reports any
+    //watermark that got crossed
+    fn contents_changed(&self) {
+        if let Some(ref watermarks) = self.watermarks {
+            let limit = match self.bound {
+                Some((limit, _)) => limit,
+                None             => unsafe { self.vec().capacity() }
+            };
+
+            watermarks.check(self.occupied(), limit);
         }
 
-        self.end = self.vec.len();
+        self.changed.notify();
+    }
 
-        unsafe {
-            //let non-reallocating pushers in again
-            self.push_lock.lock.unlock();
-        } 
+    //a channel that gets told about every change to the vec's contents, in the order
+    //they happened...lets caches invalidate exactly what changed instead of polling the
+    //version. changes made by unsafe or &mut paths (get_mut, atomic element updates)
+    //aren't reported
+    pub fn subscribe(&self) -> mpsc::Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        self.subscribed.store(true, Ordering::SeqCst);
+        receiver
     }
-}
 
-impl<'locked, T> IntoIterator for &'locked SliceGuard<'locked, T> {
-    type IntoIter = std::slice::Iter<'locked, T>;
+    //passes the huge pages hint on for a buffer that was just allocated, grown or shrunk
+    #[allow(unused_variables, clippy::ptr_arg)]
+    fn advise(&self, data : &std::vec::Vec<T>) {
+        #[cfg(all(target_os = "linux", feature = "huge-pages"))]
+        {
+            const PAGE      : usize = 4096;
+            const HUGE_PAGE : usize = 2 * 1024 * 1024;
 
-    fn into_iter(self) -> std::slice::Iter<'locked, T> {
-        //the deref on the functin call delegates this to the slice
-        self.iter()
-    }
-}
+            if !self.huge_pages {
+                return
+            }
 
-impl<'locked, T> Deref for SliceGuard<'locked, T> {
-    type Target = [T];
+            //madvise wants a page aligned start, the allocator doesn't promise one
+            let start = (data.as_ptr() as usize + PAGE - 1) & !(PAGE - 1);
+            let end   = data.as_ptr() as usize + data.capacity() * mem::size_of::<T>();
+            if end < start + HUGE_PAGE {
+                return
+            }
 
-    fn deref<'a>(&'a self) -> &'a [T] {
-        &self.vec[..self.end]
+            //only a hint, the buffer works just the same if the kernel says no
+            unsafe { libc::madvise(start as *mut libc::c_void, end - start, libc::MADV_HUGEPAGE); }
+        }
     }
-}
 
-#[unsafe_destructor]
-impl<'locked, T> Drop for SliceGuard<'locked, T> { 
-    fn drop(&mut self) {
-        self.resize_lock.lock.read_unlock();
+    //what elements count against the byte budget, 0 without one
+    fn budgeted(&self, elements : &[T]) -> usize {
+        self.budget.as_ref().map_or(0, |budget| budget.measure(elements))
     }
-}
 
-///////////////////////////////////////////////////////////////////////////////
-//                                                                           //
-//                             MUTABLE GUARDS                                //                               
-//                                                                           //
-///////////////////////////////////////////////////////////////////////////////
+    //elements weighed before the last time they were budgeted...has to be called with
+    //them still locked so nobody else settles them in between
+    fn rebudget(&self, before : usize, elements : &[T]) {
+        if let Some(ref budget) = self.budget {
+            budget.settle(before, elements);
+        }
+    }
 
-//Exlusive read and write access to a slice representing the current
-//state of the Vec...pushers can still push on the vec as long as they don't 
-//need to reallocate
-struct SliceGuardMut<'locked, T : 'locked> {
-    //the underlying vec
-    vec         : &'locked std::vec::Vec<T>,
-    //how far to slice on deref...pushers may have corrupted past here
-    end         : usize,
-    //unlock on drop
-    resize_lock : &'locked Box<StaticRwLock>,
-    //in case we need to upgrade this needs to be accuired
-    push_lock   : &'locked Box<StaticMutex>
-}   
+    //hands freshly pushed elements to every broadcast receiver...called with the push
+    //lock held so every receiver sees them in push order
+    fn broadcast_pushed(&self, pushed : &[T]) {
+        if !self.broadcasting.load(Ordering::SeqCst) {
+            return
+        }
 
-impl<'locked, T> SliceGuardMut<'locked, T> {
-    fn new(vec: &'locked std::vec::Vec<T>, resize_lock : &'locked Box<StaticRwLock>, push_lock : &'locked Box<StaticMutex>) -> SliceGuardMut<'locked, T> {
-        unsafe { resize_lock.lock.write() }
+        let mut sinks = self.sinks.lock().unwrap();
+        for t in pushed {
+            sinks.retain(|sink| sink(t));
+        }
 
-        SliceGuardMut {
-            //the underlying vec
-            vec         : vec,
-            //how far to slice on deref...pushers may have corrupted past here
-            end         : vec.len(),
-            //unlock on drop
-            resize_lock : resize_lock,
-            //in case we need to upgrade this needs to be accuired
-            push_lock   : push_lock
-        }   
+        if sinks.is_empty() {
+            self.broadcasting.store(false, Ordering::SeqCst);
+        }
     }
 
-    //this updates your view of the vec by yielding and then acquiring both locks
-    fn refresh(&mut self) { 
+    //sends event to every subscriber, forgetting the ones that hung up
+    fn announce(&self, event : ChangeEvent) {
+        if !self.subscribed.load(Ordering::SeqCst) {
+            return
+        }
+
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+
+        if subscribers.is_empty() {
+            self.subscribed.store(false, Ordering::SeqCst);
+        }
+    }
+
+    //takes the push lock, counting the times somebody else already had it
+    fn lock_for_push(&self) {
         unsafe {
-            //release pushers waiting to realloc
-            self.resize_lock.lock.write_unlock();
+            if !self.push_lock.try_lock() {
+                self.counters.contended_pushes.fetch_add(1, Ordering::Relaxed);
+                self.acquire_push_lock();
+            }
+        }
+    }
 
-            //seal off pushers
-            self.push_lock.lock.lock();
+    //takes the push lock the way the vec was built to wait for its locks
+    unsafe fn acquire_push_lock(&self) {
+        #[cfg(feature = "log")]
+        let asked = Instant::now();
 
-            //wait for immutable readers to be dropped then lock out new ones
-            self.resize_lock.lock.write();
+        self.push_lock.acquire(self.rw_lock.backend);
+
+        #[cfg(feature = "log")]
+        self.waited("push", asked);
+    }
+
+    //warns if taking a lock took longer than the vec was told to put up with
+    #[cfg(feature = "log")]
+    fn waited(&self, kind : &'static str, asked : Instant) {
+        if let Some(ref slow_locks) = self.slow_locks {
+            slow_locks.waited(kind, asked);
         }
+    }
 
-        self.end = self.vec.len();
+    //what the vec has been up to since it was made
+    pub fn stats(&self) -> Stats {
+        Stats {
+            pushes           : self.pushes.load(Ordering::SeqCst),
+            realloc_pushes   : self.counters.realloc_pushes.load(Ordering::Relaxed),
+            contended_pushes : self.counters.contended_pushes.load(Ordering::Relaxed),
+            refreshes        : self.counters.refreshes.load(Ordering::Relaxed),
+            upgrades         : self.counters.upgrades.load(Ordering::Relaxed)
+        }
+    }
 
-        unsafe {
-            //let non-reallocating pushers in again
-            self.push_lock.lock.unlock();
-        } 
+    //elements plus slots promised to permits...what the limit of a bounded vec applies to
+    fn occupied(&self) -> usize {
+        self.visible_len() + self.reserved.load(Ordering::SeqCst)
     }
 
-    //this acquires the push lock as well so you have exclusive access
-    //this is basically a scoped version of refresh that lets you exclusively mutate the whole vec 
-    //until the guard drops
-    fn upgrade(&self) -> VecGuardMut<T> { 
-        unsafe {
-            //give the pending reallocating pushers a chance to finish so no deadlock
-            self.resize_lock.lock.write_unlock(); 
-            //seal off the pushers by creating a vec guard
-            let vec_guard = VecGuardMut::new(self.vec, self.push_lock);
-            //seal off any other reader
-            self.resize_lock.lock.write(); 
+    fn push_failed(&self, error : RwVecError) -> ! {
+        match error {
+            RwVecError::Full => panic!("{}: push to a full vec (limit {})", self.label(), self.bound.map_or(0, |(limit, _)| limit)),
+            _                => panic!("{}: push failed: {}", self.label(), error)
+        }
+    }
 
-            vec_guard
+    //builds the element from its index and pushes it, then runs then with the index
+    //while the push lock is still held...also says whether the next buffer should be
+    //prepared once the push lock is let go. hands make back untouched, along with why,
+    //if the vec is full and turns the push away or the buffer can't grow
+    fn push_then<M, R, F>(&self, make : M, then : F, admission : Admission) -> Result<(R, bool), (RwVecError, M)> where M : FnOnce(usize) -> T, F : FnOnce(&RWVec<T>, usize) -> R {
+        //nothing to store...bumping the count is the whole push. bounded vecs take the
+        //push lock like everyone else so the limit check can't race, and so do watched
+        //ones so their events go out in push order
+        if RWVec::<T>::is_zst() && self.bound.is_none() && !self.subscribed.load(Ordering::SeqCst) {
+            let index = self.zst_len.fetch_add(1, Ordering::SeqCst).wrapping_add(unsafe { self.vec().len() });
+            let t = make(index);
+            self.broadcast_pushed(std::slice::from_ref(&t));
+            mem::forget(t);
+            self.pushes.fetch_add(1, Ordering::SeqCst);
+            log_event!(self, Event::Pushed { start : index, end : index + 1 });
+            self.contents_changed();
+            self.announce(ChangeEvent::Appended { range : index..index + 1 });
+            return Ok((then(self, index), false))
+        }
+
+        let data = unsafe { &mut *self.data.get() };
+
+        //compete with other pushers
+        self.lock_for_push();
+        //lets go of the push lock even if make panics
+        let mut push_hold = PushHold { lock : &self.push_lock };
+
+        if admission == Admission::Reserved {
+            //the slot turns into an element before anyone else can check the limit
+            self.reserved.fetch_sub(1, Ordering::SeqCst);
+        } else if let Some((limit, policy)) = self.bound {
+            let policy = match (admission, policy) {
+                (Admission::NoWait, OverflowPolicy::Block) => OverflowPolicy::Reject,
+                _                                          => policy
+            };
+
+            while self.occupied() >= limit {
+                match policy {
+                    OverflowPolicy::Block => {
+                        //let the consumers in and come back once they made room
+                        drop(push_hold);
+                        self.changed.wait_until(|| self.occupied() < limit);
+
+                        unsafe { self.acquire_push_lock(); }
+                        push_hold = PushHold { lock : &self.push_lock };
+                    },
+                    OverflowPolicy::DropOldest => {
+                        //everything shifts down so readers have to be out of the way
+                        let write   = self.write_locked();
+                        let evicted = if RWVec::<T>::is_zst() {
+                            //nothing to shift, one less in the count is the whole removal
+                            self.zst_len.fetch_sub(1, Ordering::SeqCst);
+                            unsafe { ptr::read(data.as_ptr()) }
+                        } else {
+                            data.remove(0)
+                        };
+
+                        let index = self.visible_len();
+                        data.push(make(index));
+                        self.rebudget(self.budgeted(std::slice::from_ref(&evicted)), &data[data.len() - 1..]);
+                        self.broadcast_pushed(&data[data.len() - 1..]);
+                        self.pushes.fetch_add(1, Ordering::SeqCst);
+                        drop(write);
+                        log_event!(self, Event::Evicted { count : 1 });
+                        log_event!(self, Event::Pushed { start : index, end : index + 1 });
+
+                        let result = then(self, index);
+                        //everything moved down one
+                        self.announce(ChangeEvent::Replaced { range : 0..index });
+                        self.announce(ChangeEvent::Appended { range : index..index + 1 });
+
+                        //safe to push again
+                        drop(push_hold);
+                        self.contents_changed();
+                        drop(evicted);
+
+                        return Ok((result, false))
+                    },
+                    OverflowPolicy::Reject | OverflowPolicy::DropNewest => return Err((RwVecError::Full, make))
+                }
+            }
+        }
+
+        //over the byte budget...the oldest go until it fits again, which has to happen
+        //before the element is made so it gets the index it will actually have
+        let mut evicted = std::vec::Vec::new();
+        if self.budget.as_ref().is_some_and(|budget| budget.over()) {
+            let budget = self.budget.as_ref().unwrap();
+            let write  = self.write_locked();
+
+            let mut count = 0;
+            let mut freed = 0;
+            while count < data.len() && budget.used.load(Ordering::SeqCst).saturating_sub(freed) > budget.limit {
+                freed += (budget.size_of)(&data[count]);
+                count += 1;
+            }
+
+            evicted = data.drain(..count).collect();
+            budget.used.fetch_sub(freed, Ordering::SeqCst);
+            drop(write);
+            log_event!(self, Event::Evicted { count : count });
+        }
+
+        //the push will cause a realloc...it happens before the element is made so a
+        //failed allocation can be reported with nothing lost
+        if data.capacity() == data.len() {
+            self.counters.realloc_pushes.fetch_add(1, Ordering::Relaxed);
+            //compete with other pushers and all the readers as well
+            let write = self.write_locked();
+            //reallocs underlying mem and copys over old values
+            #[cfg(feature = "debug-events")]
+            let from  = data.capacity();
+            let grown = reserve_wiping(data, 1);
+            //safe to read
+            drop(write);
+
+            if grown.is_err() {
+                drop(push_hold);
+                return Err((RwVecError::AllocError, make))
+            }
+
+            log_event!(self, Event::Realloc { from : from, to : data.capacity() });
+            self.advise(data);
+        }
+
+        let index = self.visible_len();
+        let t     = make(index);
+        
+        //push that doesnt affect reads
+        data.push(t);
+        self.rebudget(0, &data[data.len() - 1..]);
+        self.broadcast_pushed(&data[data.len() - 1..]);
+        self.pushes.fetch_add(1, Ordering::SeqCst);
+        log_event!(self, Event::Pushed { start : index, end : index + 1 });
+        //only keeps the shrink clock right, the shrink itself waits for exclusive access
+        unsafe { self.shrinker.observe(data); }
+        let preallocate = self.should_preallocate(data);
+        let result      = then(self, index);
+        if !evicted.is_empty() {
+            //everything moved down
+            self.announce(ChangeEvent::Replaced { range : 0..index });
         }
+        self.announce(ChangeEvent::Appended { range : index..index + 1 });
+        //safe to push again
+        drop(push_hold);
+        self.contents_changed();
+        drop(evicted);
+
+        Ok((result, preallocate))
     }
-}
 
-impl<'locked, T> IntoIterator for &'locked SliceGuardMut<'locked, T> {
-    type IntoIter = std::slice::Iter<'locked, T>;
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn reader(&self) -> SliceGuard<'_, T> {
+        self.check_poison();
+        //return a view of the current snapshot 
+        SliceGuard::new(self)
+    }
+    
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn writer(&self) -> SliceGuardMut<'_, T> {
+        self.check_poison();
+        //return a mutable, upgradable view of the current snapshot 
+        SliceGuardMut::new(self)
+    }
 
-    fn into_iter(self) -> std::slice::Iter<'locked, T> {
-        //the deref on the functin call delegates this to the slice
-        self.iter()
+    //a reader that can later turn into a writer without letting go in between, so
+    //nothing it has seen can change before it writes. plain readers carry on next to
+    //it but writers (and other upgradable readers) wait until it is dropped
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn upgradable(&self) -> UpgradableGuard<'_, T> {
+        self.check_poison();
+        unsafe { self.rw_lock.enter_upgradable(); }
+
+        UpgradableGuard { reader : SliceGuard::new(self) }
     }
-}
 
-impl<'locked, T> IntoIterator for &'locked mut SliceGuardMut<'locked, T> {
-    type IntoIter = std::slice::IterMut<'locked, T>;
+    //reader() for after a panic...whatever the panicking thread was halfway through
+    //doing to the elements is there to see
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn reader_ignoring_poison(&self) -> SliceGuard<'_, T> {
+        SliceGuard::new(self)
+    }
 
-    fn into_iter(self) -> std::slice::IterMut<'locked, T> {
-        //the deref on the functin call delegates this to the slice
-        self.into_iter()
+    //writer() for after a panic, e.g. to repair the elements before clear_poison()
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn writer_ignoring_poison(&self) -> SliceGuardMut<'_, T> {
+        SliceGuardMut::new(self)
     }
-}
 
-impl<'locked, T> Deref for SliceGuardMut<'locked, T> {
-    type Target = [T];
+    //reader() that fails instead of waiting on a writer (or panicking on poison)
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn try_reader(&self) -> Result<SliceGuard<'_, T>, RwVecError> {
+        self.reader_until(Instant::now(), RwVecError::WouldBlock)
+    }
 
-    fn deref<'a>(&'a self) -> &'a [T] {
-        &self.vec[..self.end]
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn try_reader_timeout(&self, timeout : Duration) -> Result<SliceGuard<'_, T>, RwVecError> {
+        self.reader_until(Instant::now() + timeout, RwVecError::TimedOut)
     }
-}
 
-impl<'locked, T> DerefMut for SliceGuardMut<'locked, T> {
-    fn deref_mut<'a>(&'a mut self) -> &'a mut [T] {
-        &mut self.vec[..self.end]
+    //writer() that fails instead of waiting on readers and other writers
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn try_writer(&self) -> Result<SliceGuardMut<'_, T>, RwVecError> {
+        self.writer_until(Instant::now(), RwVecError::WouldBlock)
     }
-}
 
-#[unsafe_destructor]
-impl<'locked, T> Drop for SliceGuardMut<'locked, T> { 
-    fn drop(&mut self) {
-        unsafe { self.resize_lock.lock.write_unlock(); }
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn try_writer_timeout(&self, timeout : Duration) -> Result<SliceGuardMut<'_, T>, RwVecError> {
+        self.writer_until(Instant::now() + timeout, RwVecError::TimedOut)
     }
-}
 
-//Exclusive read and write acces to the whole vec...pushers get blocked while
-//they wait for this to drop
-struct VecGuardMut<'locked, T : 'locked> {
-    //exclusive access to the vec
-    vec    : &'locked std::vec::Vec<T>,
-    //unlock this on drop 
-    lock   : &'locked Box<StaticMutex>
-}
+    //error is what to report if the lock can't be had by deadline
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn reader_until(&self, deadline : Instant, error : RwVecError) -> Result<SliceGuard<'_, T>, RwVecError> {
+        if self.is_poisoned() {
+            return Err(RwVecError::Poisoned)
+        }
 
-impl<'locked, T> VecGuardMut<'locked, T> {
-    fn new(vec : &'locked std::vec::Vec<T>, push_lock : &'locked Box<StaticMutex>) -> VecGuardMut<'locked, T> {
-        unsafe { push_lock.lock.lock() }
+        if RWVec::<T>::is_zst() {
+            return Ok(SliceGuard::locked(self, ReadSlot::none()))
+        }
 
-        VecGuardMut {
-            vec : vec,
-            lock : push_lock
+        match unsafe { self.rw_lock.read_until(deadline) } {
+            Some(read) => Ok(SliceGuard::locked(self, read)),
+            None       => Err(error)
         }
     }
-}
 
-impl<'locked, T> IntoIterator for &'locked VecGuardMut<'locked, T> {
-    type IntoIter = std::slice::Iter<'locked, T>;
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn writer_until(&self, deadline : Instant, error : RwVecError) -> Result<SliceGuardMut<'_, T>, RwVecError> {
+        if self.is_poisoned() {
+            return Err(RwVecError::Poisoned)
+        }
 
-    fn into_iter(self) -> std::slice::Iter<'locked, T> {
-        //the deref on the functin call delegates this to the vec
-        self.into_iter()
+        if unsafe { self.rw_lock.write_until(deadline) } {
+            Ok(SliceGuardMut::locked(self))
+        } else {
+            Err(error)
+        }
     }
-}
 
-impl<'locked, T> IntoIterator for &'locked mut VecGuardMut<'locked, T> {
-    type IntoIter = std::slice::IterMut<'locked, T>;
+    //a thread panicked while it held a writer or exclusive access, so the elements
+    //might be half way through a change
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::SeqCst)
+    }
 
-    fn into_iter(self) -> std::slice::IterMut<'locked, T> {
-        //the deref on the functin call delegates this to the vec
-        self.into_iter()
+    //declares the contents fine again, after checking or repairing them
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::SeqCst);
     }
-}
 
-impl<'locked, T> Deref for VecGuardMut<'locked, T> {
-    type Target = std::vec::Vec<T>;
+    fn check_poison(&self) {
+        if self.is_poisoned() {
+            panic!("{}: poisoned by a panic while it was being written to", self.label());
+        }
+    }
 
-    fn deref<'a>(&'a self) -> &'a std::vec::Vec<T> {
-        self.vec
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|name| &name[..])
     }
-}
 
+    //how this vec refers to itself in panics and diagnostics
+    fn label(&self) -> String {
+        match self.name {
+            Some(ref name) => format!("RWVec '{}'", name),
+            None           => "RWVec".to_string()
+        }
+    }
 
-impl<'locked, T> DerefMut for VecGuardMut<'locked, T> {
-    fn deref_mut<'a>(&'a mut self) -> &'a mut std::vec::Vec<T> {
-        &mut *self.vec
+    //changes whenever the contents might have...every push and every release of the
+    //write lock moves it forward
+    pub fn version(&self) -> usize {
+        self.pushes.load(Ordering::SeqCst) + self.rw_lock.generation()
     }
-}
 
-#[unsafe_destructor]
-impl<'locked, T> Drop for VecGuardMut<'locked, T> { 
-    fn drop(&mut self) {
-        self.lock.lock.unlock();
+    //blocks until every SliceGuard that existed when this was called has been dropped...
+    //readers created after the call don't hold this up
+    pub fn wait_for_readers(&self) {
+        self.fence_readers().wait()
     }
-}
 
-///////////////////////////////////////////////////////////////////////////////
-//                                                                           //
-//                                 TESTS                                     //                               
-//                                                                           //
-///////////////////////////////////////////////////////////////////////////////
+    //non-blocking version of wait_for_readers...the fence can be polled, waited on,
+    //or awaited from any executor
+    pub fn fence_readers(&self) -> ReaderFence<'_, T> {
+        ReaderFence {
+            owner : self,
+            epoch : None
+        }
+    }
+
+    fn is_zst() -> bool {
+        mem::size_of::<T>() == 0
+    }
+
+    //swaps the contents out for an empty vec in one exclusive section and hands back
+    //everything that was in there
+    pub fn take_all(&self) -> std::vec::Vec<T> {
+        self.take_all_reserving(self.drain_floor.unwrap_or(0))
+    }
+
+    //take_all, but the vec left behind already has room for capacity elements...it is
+    //allocated before any lock is taken
+    pub fn take_all_reserving(&self, capacity : usize) -> std::vec::Vec<T> {
+        let fresh   = std::vec::Vec::with_capacity(capacity);
+        let mut vec = self.exclusive();
+        mem::replace(&mut *vec, fresh)
+    }
+
+    //makes room for additional more elements without aborting if the allocation fails...
+    //readers are only locked out if the buffer actually has to move
+    pub fn try_reserve(&self, additional : usize) -> Result<(), TryReserveError> {
+        if RWVec::<T>::is_zst() {
+            return Ok(())
+        }
+
+        unsafe { self.acquire_push_lock(); }
+        let _push = PushHold { lock : &self.push_lock };
+
+        let data = unsafe { self.vec() };
+        if data.capacity() - data.len() >= additional {
+            return Ok(())
+        }
+
+        let _write = self.write_locked();
+        #[cfg(feature = "debug-events")]
+        let from   = data.capacity();
+        let grown  = reserve_wiping(data, additional);
+        if grown.is_ok() {
+            log_event!(self, Event::Realloc { from : from, to : data.capacity() });
+            self.advise(data);
+        }
+        grown
+    }
+
+    //drops everything in one exclusive section
+    pub fn clear(&self) {
+        let mut vec = self.exclusive();
+        vec.clear();
+        self.release_drained(&mut vec);
+    }
+
+    //moves the elements in range out in one exclusive section, in order
+    pub fn drain<R>(&self, range : R) -> std::vec::Vec<T> where R : RangeBounds<usize> {
+        let mut vec = self.exclusive();
+        let drained = vec.drain(range).collect();
+        self.release_drained(&mut vec);
+        drained
+    }
+
+    //moves out every element pred picks, in order, and keeps the rest in order...all
+    //in one exclusive section. pred can look at (and change) each element on the way
+    pub fn extract_if<F>(&self, mut pred : F) -> std::vec::Vec<T> where F : FnMut(&mut T) -> bool {
+        let mut vec       = self.exclusive();
+        let mut extracted = std::vec::Vec::new();
+
+        //filtered in place like retain, the keepers shifting down over the gaps, so no
+        //copy of an element is left behind in a temporary buffer. len stays 0 until the
+        //end, so if pred panics the rest is leaked rather than dropped twice
+        let len = vec.len();
+        unsafe {
+            vec.set_len(0);
+            let base     = vec.as_mut_ptr();
+            let mut kept = 0;
+
+            for i in 0..len {
+                let t = base.offset(i as isize);
+                if pred(&mut *t) {
+                    if let Err(error) = reserve_wiping(&mut extracted, 1) {
+                        panic!("{}: extract_if: {}", self.label(), error);
+                    }
+                    extracted.push(ptr::read(t));
+                } else {
+                    if kept != i {
+                        ptr::copy_nonoverlapping(t, base.offset(kept as isize), 1);
+                    }
+                    kept += 1;
+                }
+            }
+
+            vec.set_len(kept);
+        }
+
+        self.release_drained(&mut vec);
+        extracted
+    }
+
+    //empties the vec in one exclusive section, sorting the elements into the ones pred
+    //picks and the rest, both in order. RWVec::from_vec turns either side back into a
+    //shared vec. see extract_if for keeping the rest in place
+    pub fn partition<F>(&self, mut pred : F) -> (std::vec::Vec<T>, std::vec::Vec<T>) where F : FnMut(&T) -> bool {
+        let mut vec    = self.exclusive();
+        let mut picked = std::vec::Vec::new();
+        let mut rest   = std::vec::Vec::new();
+
+        //both sides get room for everything up front so neither leaves a grown out of
+        //buffer behind, and the vec keeps its own like drain does. len stays 0 while the
+        //elements move, same as extract_if
+        let len = vec.len();
+        if picked.try_reserve_exact(len).is_err() || rest.try_reserve_exact(len).is_err() {
+            panic!("{}: partition: {}", self.label(), RwVecError::AllocError);
+        }
+
+        unsafe {
+            vec.set_len(0);
+            let base = vec.as_ptr();
+
+            for i in 0..len {
+                let t = ptr::read(base.offset(i as isize));
+                if pred(&t) {
+                    picked.push(t);
+                } else {
+                    rest.push(t);
+                }
+            }
+        }
+
+        self.release_drained(&mut vec);
+        (picked, rest)
+    }
+
+    //drops every element that has the same key as the one before it, in one exclusive
+    //section...sort by the same key first to drop every duplicate
+    pub fn dedup_by_key<K, F>(&self, key : F) where K : PartialEq, F : FnMut(&mut T) -> K {
+        let mut vec = self.exclusive();
+        vec.dedup_by_key(key);
+        self.release_drained(&mut vec);
+    }
+
+    //runs f over every element, chunk elements per hold of the write lock...readers and
+    //reallocating pushers get their turn in between, so a long pass doesn't freeze them.
+    //the pass covers the elements there when it started. pushes don't disturb it, but an
+    //exclusive section in between that removes or reorders elements can make it skip
+    //some or visit some twice
+    pub fn apply_in_place<F>(&self, chunk : usize, mut f : F) where F : FnMut(&mut T) {
+        assert!(chunk > 0, "chunk size must be at least 1");
+
+        let len       = self.visible_len();
+        let mut start = 0;
+
+        while start < len {
+            let mut guard = self.writer();
+            let end       = std::cmp::min(std::cmp::min(start + chunk, len), guard.len());
+            if start >= end {
+                return
+            }
+
+            for t in guard[start..end].iter_mut() {
+                f(t);
+            }
+
+            start = end;
+        }
+    }
+
+    //moves the first n elements to the end...elements only change places, so this just
+    //takes the write lock and pushers that don't realloc carry on. panics if n > len
+    pub fn rotate_left(&self, n : usize) {
+        self.writer().rotate_left(n);
+    }
+
+    //moves the last n elements to the front, see rotate_left
+    pub fn rotate_right(&self, n : usize) {
+        self.writer().rotate_right(n);
+    }
+
+    //moves every element of other onto the end of this one, in order, leaving other
+    //empty...both vecs are locked at once (lower address first, so two merges going
+    //opposite ways can't deadlock) and nobody sees the elements in neither or both.
+    //it all moves or none of it does: a bounded vec that can't fit everything hands
+    //back Full unless it drops the oldest (merge never waits for room), a budgeted one
+    //drops the oldest until it fits like a push would, and AllocError means the buffer
+    //couldn't grow
+    pub fn merge(&self, other : &RWVec<T>) -> Result<(), RwVecError> {
+        if ptr::eq(self, other) {
+            return Ok(())
+        }
+
+        let self_first   = (self as *const RWVec<T> as usize) < (other as *const RWVec<T> as usize);
+        let mut first    = if self_first { self.exclusive() } else { other.exclusive() };
+        let mut second   = if self_first { other.exclusive() } else { self.exclusive() };
+        let (into, from) = if self_first { (&mut first, &mut second) } else { (&mut second, &mut first) };
+
+        //how many of the oldest have to go to stay under the limit...permits can't be
+        //handed out while we hold the push lock, so the count holds still
+        let mut evict = 0;
+        if let Some((limit, policy)) = self.bound {
+            let occupied = into.len() + from.len() + self.reserved.load(Ordering::SeqCst);
+            if occupied > limit {
+                if policy != OverflowPolicy::DropOldest {
+                    return Err(RwVecError::Full)
+                }
+                evict = occupied - limit;
+            }
+        }
+
+        //append would grow into through std and leave the old buffer unwiped
+        if reserve_wiping(into, from.len()).is_err() {
+            return Err(RwVecError::AllocError)
+        }
+        into.append(from);
+
+        //the budget is weighed again once the guard lets go, this just makes it fit
+        if let Some(ref budget) = self.budget {
+            let mut used = budget.measure(into);
+            while evict < into.len() && used > budget.limit {
+                used  -= (budget.size_of)(&into[evict]);
+                evict += 1;
+            }
+        }
+
+        if evict > 0 {
+            let evict = std::cmp::min(evict, into.len());
+            into.drain(..evict);
+            log_event!(self, Event::Evicted { count : evict });
+        }
+
+        other.release_drained(from);
+        Ok(())
+    }
+
+    //gives back the capacity a drain left unused while we still have exclusive access,
+    //if the vec was built to
+    fn release_drained(&self, vec : &mut std::vec::Vec<T>) {
+        if let Some(floor) = self.drain_floor {
+            shrink_wiping(vec, floor);
+        }
+    }
+
+    //takes the last element off...needs exclusive access since readers could be
+    //looking at it
+    pub fn pop(&self) -> Option<T> {
+        //don't lock everyone out (and move the version) just to find nothing
+        if self.visible_len() == 0 {
+            return None
+        }
+
+        let mut vec = self.exclusive();
+        vec.tail_only();
+        vec.pop()
+    }
+
+    //blocks until there is something to pop...together with push this makes a simple
+    //work queue (last in first out) whose backlog can still be looked at with reader()
+    pub fn pop_wait(&self) -> T {
+        loop {
+            let seen = self.pushes.load(Ordering::SeqCst);
+
+            if let Some(t) = self.pop() {
+                return t
+            }
+
+            //nothing there...sleep until something gets pushed after we looked
+            self.changed.wait_until(|| self.has_new_elements(seen));
+        }
+    }
+
+    //pop_wait that gives up with None once timeout has passed
+    pub fn pop_timeout(&self, timeout : Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let seen = self.pushes.load(Ordering::SeqCst);
+
+            if let Some(t) = self.pop() {
+                return Some(t)
+            }
+
+            let now = Instant::now();
+            if now >= deadline || !self.changed.wait_until_timeout(deadline - now, || self.has_new_elements(seen)) {
+                return None
+            }
+        }
+    }
+
+    //takes up to max elements off the end in one exclusive section, in the order they
+    //were pushed...lets another worker grab a whole chunk for the price of one lock
+    pub fn steal_batch(&self, max : usize) -> std::vec::Vec<T> {
+        if max == 0 || self.visible_len() == 0 {
+            return std::vec::Vec::new()
+        }
+
+        let mut vec = self.exclusive();
+        vec.tail_only();
+        let at = vec.len().saturating_sub(max);
+        vec.split_off(at)
+    }
+
+    //something got pushed since seen, or something is sitting there anyway (put there
+    //through an upgraded guard, which doesn't count as a push)
+    fn has_new_elements(&self, seen : usize) -> bool {
+        self.pushes.load(Ordering::SeqCst) != seen || self.visible_len() != 0
+    }
+
+    //swaps value into the slot and hands back what was there, all under the write lock
+    //so nobody observes the slot in between
+    pub fn replace(&self, index : usize, value : T) -> T {
+        let _write = self.write_locked();
+        let slot   = unsafe { self.element(index) };
+        let old    = mem::replace(slot, value);
+        self.rebudget(self.budgeted(std::slice::from_ref(&old)), std::slice::from_ref(slot));
+        self.announce(ChangeEvent::Replaced { range : index..index + 1 });
+        old
+    }
+
+    //direct access to the underlying vec without any locking...having &mut self means
+    //nobody else can be holding a guard or pushing, e.g. after Arc::get_mut succeeds
+    //or while the vec is still being set up
+    pub fn get_mut(&mut self) -> &mut std::vec::Vec<T> {
+        if RWVec::<T>::is_zst() {
+            //fold the pushed count into the vec so its len is the real one
+            let pushed = mem::replace(self.zst_len.get_mut(), 0);
+            let vec    = self.data.get_mut();
+            let len    = vec.len().wrapping_add(pushed);
+            unsafe { vec.set_len(len); }
+        }
+
+        self.data.get_mut()
+    }
+
+    //both locks, in the same order pushers take them...nobody else can see or touch the
+    //vec until this is dropped
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn exclusive(&self) -> ExclusiveGuard<'_, T> {
+        let vec = VecGuardMut::new(self);
+        unsafe { self.rw_lock.write(); }
+
+        ExclusiveGuard {
+            vec   : vec,
+            write : WriteHold { lock : &self.rw_lock }
+        }
+    }
+
+    //just the write lock...enough to touch elements that are already there, since
+    //pushers only ever append past them
+    fn write_locked(&self) -> WriteHold<'_> {
+        unsafe { self.rw_lock.write(); }
+        WriteHold { lock : &self.rw_lock }
+    }
+
+    //has to be called with at least the write lock held so the element can't move...
+    //that lock is also what keeps the &mut from aliasing
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn element(&self, index : usize) -> &mut T {
+        let len = self.visible_len();
+        if index >= len {
+            panic!("{}: index {} out of bounds (len {})", self.label(), index, len);
+        }
+
+        &mut *self.vec().as_mut_ptr().offset(index as isize)
+    }
+
+    //callers are responsible for holding whatever lock makes this access ok, and for
+    //not keeping two of these around where they'd overlap
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn vec(&self) -> &mut std::vec::Vec<T> {
+        &mut *self.data.get()
+    }
+
+    //the len readers should see...zero sized elements live in the count plus whatever
+    //len get_mut left the vec at (the count can wrap below zero to make up for it)
+    fn visible_len(&self) -> usize {
+        if RWVec::<T>::is_zst() {
+            self.zst_len.load(Ordering::SeqCst).wrapping_add(unsafe { self.vec().len() })
+        } else {
+            unsafe { self.vec().len() }
+        }
+    }
+
+    //has to be called with the push lock held so len and capacity are stable
+    fn should_preallocate(&self, data : &std::vec::Vec<T>) -> bool {
+        match self.preallocate_at {
+            Some(threshold) => {
+                !RWVec::<T>::is_zst() &&
+                data.capacity() != 0 &&
+                data.len() as f32 >= data.capacity() as f32 * threshold
+            },
+            None => false
+        }
+    }
+
+    //grows the buffer before a push has to, so no push ends up allocating with every
+    //reader locked out...only the allocation happens off to the side. the copy needs
+    //the write lock as well, since elements can be changed through &T without it
+    //(atomics, store_at, RWBitVec) and anything changed in the old buffer after a copy
+    //taken under just a read lock would be lost when the buffers are swapped
+    fn preallocate(&self) {
+        //somebody else is already preparing the next buffer
+        if self.preallocating.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return
+        }
+
+        let data = unsafe { &mut *self.data.get() };
+
+        //only moves under the push lock
+        let capacity = unsafe {
+            self.acquire_push_lock();
+            let capacity = data.capacity();
+            self.push_lock.unlock();
+            capacity
+        };
+
+        //the expensive part...pushers and readers carry on while this happens. the new
+        //buffer comes from Vec so it is aligned for T no matter how over-aligned T is,
+        //and the copy below is in whole elements
+        let mut next = std::vec::Vec::new();
+        if next.try_reserve_exact(capacity * 2).is_err() {
+            //no memory to get ahead with...the push that fills the buffer will try
+            //again, and report it if that fails too
+            self.preallocating.store(false, Ordering::SeqCst);
+            return
+        }
+
+        unsafe {
+            //the exclusive section, down to a copy and a swap
+            self.acquire_push_lock();
+            self.rw_lock.write();
+
+            //a push that had to realloc in the meantime has already grown the buffer,
+            //so just throw the work away
+            if data.capacity() == capacity {
+                let len = data.len();
+                ptr::copy_nonoverlapping(data.as_ptr(), next.as_mut_ptr(), len);
+
+                //the elements now live in next...the old buffer just needs to be freed
+                next.set_len(len);
+                data.set_len(0);
+                mem::swap(data, &mut next);
+                log_event!(self, Event::Realloc { from : next.capacity(), to : data.capacity() });
+                self.advise(data);
+            }
+
+            self.rw_lock.write_unlock();
+            self.push_lock.unlock();
+        }
+
+        self.preallocating.store(false, Ordering::SeqCst);
+
+        //free the old (or unused) buffer outside of the locks...its len is 0 so
+        //nothing gets dropped twice
+        free_wiping(next);
+    }
+}
+
+impl<T> RWVec<T> {
+    //channel style handles over the vec...every element pushed through a Sender goes to
+    //exactly one Receiver, oldest first, while reader() still shows the backlog.
+    //receiving removes from the front so it costs a shift of the remaining backlog
+    pub fn as_queue(vec : &Arc<RWVec<T>>) -> (Sender<T>, Receiver<T>) {
+        let queue = Arc::new(Queue {
+            vec     : vec.clone(),
+            senders : AtomicUsize::new(1)
+        });
+
+        (Sender { queue : queue.clone() }, Receiver { queue : queue })
+    }
+}
+
+impl<T> RWVec<T> {
+    //a handle onto just the newest element, for consumers that never care about the
+    //rest...borrow() reads it and changed() sleeps until something newer is pushed
+    pub fn watch_last(vec : &Arc<RWVec<T>>) -> WatchLast<T> {
+        WatchLast {
+            vec  : vec.clone(),
+            seen : vec.pushes.load(Ordering::SeqCst)
+        }
+    }
+
+    //gives up the all powerful Arc for one handle that can only push and one that can
+    //only read, so it's clear from the types which components change the vec. both can
+    //be cloned and handed out further
+    pub fn split(vec : Arc<RWVec<T>>) -> (Producer<T>, ReaderFactory<T>) {
+        (Producer { vec : vec.clone() }, ReaderFactory { vec : vec })
+    }
+}
+
+impl<T : 'static> RWVec<T> {
+    //for vecs that live as long as the process does (registries, global tables)...the
+    //vec is never freed, in exchange it can be referenced from anywhere without passing
+    //Arcs around. the guards it hands out are StaticSliceGuard and friends
+    pub fn leak(vec : Arc<RWVec<T>>) -> &'static RWVec<T> {
+        unsafe { &*Arc::into_raw(vec) }
+    }
+}
+
+impl<T : Default> RWVec<T> {
+    //moves the element out and leaves a default in its place...only the write lock is
+    //taken, pushers that don't need to realloc carry on
+    pub fn take(&self, index : usize) -> T {
+        //build the replacement before locking anyone out
+        let default = T::default();
+        let _write  = self.write_locked();
+        let slot    = unsafe { self.element(index) };
+        let taken   = mem::replace(slot, default);
+        self.rebudget(self.budgeted(std::slice::from_ref(&taken)), std::slice::from_ref(slot));
+        self.announce(ChangeEvent::Replaced { range : index..index + 1 });
+        taken
+    }
+}
+
+impl<T> RWVec<T> {
+    //moves every element of other onto the end, leaving other empty but with its
+    //buffer...same single reserve and memcpy as extend_from_slice
+    pub fn append(&self, other : &mut std::vec::Vec<T>) {
+        if other.is_empty() {
+            return
+        }
+
+        //the limit and the budget have to be checked element by element
+        if self.bound.is_some() || self.budget.is_some() {
+            for t in other.drain(..) {
+                self.push(t);
+            }
+
+            return
+        }
+
+        //other keeps them if the buffer can't grow
+        if let Err(error) = unsafe { self.append_unchecked(other) } {
+            self.push_failed(error);
+        }
+
+        //the elements belong to the vec now
+        unsafe { other.set_len(0); }
+    }
+
+    //appends bitwise copies of src with a single reserve and a single memcpy under one
+    //hold of the push lock...the write lock is only taken for the reserve, if one is
+    //needed. the caller has to make sure the originals never get dropped (T is Copy, or
+    //they are forgotten) and that the vec is neither bounded nor budgeted. nothing is
+    //copied if the buffer can't grow, the error says so
+    unsafe fn append_unchecked(&self, src : &[T]) -> Result<(), RwVecError> {
+        if RWVec::<T>::is_zst() && !self.subscribed.load(Ordering::SeqCst) {
+            let start = self.zst_len.fetch_add(src.len(), Ordering::SeqCst).wrapping_add(self.vec().len());
+            self.broadcast_pushed(src);
+            self.pushes.fetch_add(src.len(), Ordering::SeqCst);
+            log_event!(self, Event::Pushed { start : start, end : start + src.len() });
+            self.contents_changed();
+            self.announce(ChangeEvent::Appended { range : start..start + src.len() });
+            return Ok(())
+        }
+
+        let data = self.vec();
+
+        //compete with other pushers
+        self.lock_for_push();
+        //lets go of the push lock even if a broadcast sink panics
+        let push_hold = PushHold { lock : &self.push_lock };
+
+        if data.capacity() - data.len() < src.len() {
+            self.counters.realloc_pushes.fetch_add(1, Ordering::Relaxed);
+            //the one realloc moves the buffer, so readers have to be out of the way
+            #[cfg(feature = "debug-events")]
+            let from  = data.capacity();
+            let write = self.write_locked();
+            let grown = reserve_wiping(data, src.len());
+            drop(write);
+
+            if grown.is_err() {
+                return Err(RwVecError::AllocError)
+            }
+
+            log_event!(self, Event::Realloc { from : from, to : data.capacity() });
+            self.advise(data);
+        }
+
+        //the copy lands past len so readers don't care about it
+        let len = data.len();
+        ptr::copy_nonoverlapping(src.as_ptr(), data.as_mut_ptr().offset(len as isize), src.len());
+        data.set_len(len + src.len());
+        self.broadcast_pushed(src);
+        self.pushes.fetch_add(src.len(), Ordering::SeqCst);
+        log_event!(self, Event::Pushed { start : len, end : len + src.len() });
+
+        //see push_then, the shrink waits for exclusive access
+        self.shrinker.observe(data);
+        let preallocate = self.should_preallocate(data);
+        self.announce(ChangeEvent::Appended { range : len..len + src.len() });
+
+        //safe to push again
+        drop(push_hold);
+        self.contents_changed();
+
+        if preallocate {
+            self.preallocate();
+        }
+
+        Ok(())
+    }
+}
+
+impl<T : PartialEq> RWVec<T> {
+    //dedup_by_key with the element as its own key
+    pub fn dedup(&self) {
+        let mut vec = self.exclusive();
+        vec.dedup();
+        self.release_drained(&mut vec);
+    }
+}
+
+impl<T : Ord> RWVec<T> {
+    //reorders the elements so the one at index is the one a sort would put there, with
+    //nothing bigger before it and nothing smaller after...in place under the write lock
+    pub fn select_nth_unstable(&self, index : usize) {
+        self.writer().select_nth_unstable(index);
+    }
+}
+
+impl<T : Ord + Clone> RWVec<T> {
+    //the element a sort would put at index (e.g. len * 99 / 100 for the p99), or None
+    //past the end...the read lock is only held while cloning the contents out, the
+    //vec itself is left alone
+    pub fn nth_smallest(&self, index : usize) -> Option<T> {
+        let mut copy = self.reader().to_vec();
+        if index >= copy.len() {
+            return None
+        }
+
+        copy.select_nth_unstable(index);
+        Some(copy.swap_remove(index))
+    }
+
+    //the k largest elements, largest first, cloned out the same way
+    pub fn top_k(&self, k : usize) -> std::vec::Vec<T> {
+        if k == 0 {
+            return std::vec::Vec::new()
+        }
+
+        let mut copy = self.reader().to_vec();
+        if k < copy.len() {
+            let at = copy.len() - k;
+            copy.select_nth_unstable(at);
+            copy.drain(..at);
+        }
+
+        copy.sort_unstable_by(|a, b| b.cmp(a));
+        copy
+    }
+}
+
+impl<T : Copy> RWVec<T> {
+    //appends the whole slice with a single reserve and a single memcpy under one hold of
+    //the push lock...the write lock is only taken for the reserve, if one is needed
+    pub fn extend_from_slice(&self, src : &[T]) {
+        if src.is_empty() {
+            return
+        }
+
+        //the limit and the budget have to be checked element by element
+        if self.bound.is_some() || self.budget.is_some() {
+            for t in src {
+                self.push(*t);
+            }
+
+            return
+        }
+
+        if let Err(error) = unsafe { self.append_unchecked(src) } {
+            self.push_failed(error);
+        }
+    }
+}
+
+impl<T : Sync> RWVec<T> {
+    //runs f over every element of the current snapshot, split into one contiguous range
+    //per thread...the read lock is held until every thread is done
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn for_each_parallel<F>(&self, threads : usize, f : F) where F : Fn(&T) + Sync {
+        let guard = self.reader();
+        let chunk = parallel_chunk_len(guard.len(), threads);
+        if chunk == 0 {
+            return
+        }
+
+        let f = &f;
+        std::thread::scope(|scope| {
+            for part in guard.chunks(chunk) {
+                scope.spawn(move || for t in part { f(t) });
+            }
+        });
+    }
+}
+
+impl<T : Send> RWVec<T> {
+    //for_each_parallel through a writer, so f gets each element mutably...pushers keep
+    //going, readers wait until every thread is done
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn for_each_parallel_mut<F>(&self, threads : usize, f : F) where F : Fn(&mut T) + Sync {
+        let mut guard = self.writer();
+        let chunk     = parallel_chunk_len(guard.len(), threads);
+        if chunk == 0 {
+            return
+        }
+
+        let f = &f;
+        std::thread::scope(|scope| {
+            for part in guard.chunks_mut(chunk) {
+                scope.spawn(move || for t in part { f(t) });
+            }
+        });
+    }
+}
+
+//how many elements each thread gets so that len is covered by at most threads ranges
+fn parallel_chunk_len(len : usize, threads : usize) -> usize {
+    let threads = if threads == 0 { 1 } else { threads };
+    len.div_ceil(threads)
+}
+
+impl<T> fmt::Debug for RWVec<T> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {{ len: {}, version: {} }}", self.label(), self.visible_len(), self.version())
+    }
+}
+
+impl<T> Drop for RWVec<T> {
+    fn drop(&mut self) {
+        //give the zero sized elements a len so their destructors still run
+        if RWVec::<T>::is_zst() {
+            let len = self.visible_len();
+            unsafe { self.vec().set_len(len) }
+        }
+
+        //the elements go first, then the buffer they were in gets wiped
+        #[cfg(feature = "zeroize")]
+        {
+            let mut vec = mem::take(self.data.get_mut());
+            unsafe {
+                ptr::drop_in_place(&mut vec[..]);
+                vec.set_len(0);
+            }
+            free_wiping(vec);
+        }
+    }
+}
+
+//all the knobs for building an RWVec...anything left alone behaves like a plain Vec
+pub struct Builder<T> {
+    name           : Option<String>,
+    capacity       : usize,
+    preallocate_at : Option<f32>,
+    shrink_policy  : Option<ShrinkPolicy>,
+    bound          : Option<(usize, OverflowPolicy)>,
+    watermarks     : Option<Watermarks>,
+    drain_floor    : Option<usize>,
+    budget         : Option<(usize, fn(&T) -> usize)>,
+    #[cfg(feature = "huge-pages")]
+    huge_pages     : bool,
+    lock_backend   : LockBackend,
+    #[cfg(feature = "watchdog")]
+    watchdog       : Option<(Duration, Arc<dyn Fn(&HeldGuard) + Send + Sync>)>,
+    #[cfg(feature = "log")]
+    slow_locks     : Option<(Duration, Duration)>,
+    marker         : PhantomData<T>
+}
+
+impl<T> Builder<T> {
+    fn new() -> Builder<T> {
+        Builder {
+            name           : None,
+            capacity       : 0,
+            preallocate_at : None,
+            shrink_policy  : None,
+            bound          : None,
+            watermarks     : None,
+            drain_floor    : None,
+            budget         : None,
+            #[cfg(feature = "huge-pages")]
+            huge_pages     : false,
+            lock_backend   : LockBackend::Blocking,
+            #[cfg(feature = "watchdog")]
+            watchdog       : None,
+            #[cfg(feature = "log")]
+            slow_locks     : None,
+            marker         : PhantomData
+        }
+    }
+
+    //a label for panics, watchdog reports and anything else that needs to say which vec
+    pub fn name<S>(mut self, name : S) -> Builder<T> where S : Into<String> {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn capacity(mut self, capacity : usize) -> Builder<T> {
+        self.capacity = capacity;
+        self
+    }
+
+    //once len crosses this fraction of the capacity (0.0 - 1.0) the next push prepares
+    //a buffer twice the size in the background and swaps it in, so the realloc never
+    //happens while all the readers are locked out
+    pub fn preallocate_at(mut self, threshold : f32) -> Builder<T> {
+        assert!(threshold > 0.0 && threshold <= 1.0, "preallocation threshold must be in (0, 1]");
+        self.preallocate_at = Some(threshold);
+        self
+    }
+
+    //once len has stayed below this fraction of the capacity for at least the given
+    //duration the buffer gets shrunk down to twice its len...this only happens when
+    //exclusive access is held anyway (a drain, a pop, dropping an upgraded guard) so it
+    //never takes the locks by itself. with a zero duration the drain that takes it
+    //below the watermark shrinks it right away
+    pub fn shrink_when_below(mut self, watermark : f32, after : Duration) -> Builder<T> {
+        assert!(watermark > 0.0 && watermark < 0.5, "shrink watermark must be in (0, 0.5)");
+        self.shrink_policy = Some(ShrinkPolicy { watermark : watermark, after : after });
+        self
+    }
+
+    //clear and drain shrink the buffer down to floor (but never below what's left) in
+    //the same exclusive section, and take_all leaves a buffer of floor behind...saves
+    //taking the locks again just to give memory back
+    pub fn shrink_on_drain(mut self, floor : usize) -> Builder<T> {
+        self.drain_floor = Some(floor);
+        self
+    }
+
+    //how threads wait for this vec's push and resize locks...defaults to Blocking
+    pub fn lock_backend(mut self, backend : LockBackend) -> Builder<T> {
+        self.lock_backend = backend;
+        self
+    }
+
+    //never holds more than limit elements...policy decides what a push to a full vec
+    //does. the check happens under the push lock, so the limit is exact
+    pub fn bounded(mut self, limit : usize, policy : OverflowPolicy) -> Builder<T> {
+        assert!(limit > 0, "a bounded vec needs room for at least one element");
+        self.bound = Some((limit, policy));
+        self
+    }
+
+    //caps the vec by what its elements weigh rather than how many there are...size_of
+    //says how many bytes one element accounts for (its heap allocations included, if
+    //those should count). once the total is past max_bytes the oldest elements are
+    //evicted before the next push goes in, so it overshoots by at most one element.
+    //evicting waits out every live reader like any other exclusive access
+    pub fn byte_budget(mut self, max_bytes : usize, size_of : fn(&T) -> usize) -> Builder<T> {
+        self.budget = Some((max_bytes, size_of));
+        self
+    }
+
+    //calls callback with High once the vec fills past high (a fraction of the bound, or
+    //of the current capacity for unbounded vecs) and with Low once it has drained back
+    //down to low. it runs on whichever thread moved the len, possibly with the vec still
+    //locked, so it should hand the news off rather than touch the vec
+    pub fn watermarks<F>(mut self, low : f32, high : f32, callback : F) -> Builder<T> where F : Fn(Watermark) + Send + Sync + 'static {
+        assert!(low >= 0.0 && low < high && high <= 1.0, "watermarks must satisfy 0 <= low < high <= 1");
+        self.watermarks = Some(Watermarks {
+            low      : low,
+            high     : high,
+            above    : AtomicBool::new(false),
+            callback : Box::new(callback)
+        });
+        self
+    }
+
+    //asks the kernel to back the buffer with transparent huge pages every time it is
+    //allocated or moved, which cuts TLB misses on scans over very large vecs. only
+    //buffers spanning at least a whole huge page are worth it. does nothing outside
+    //linux
+    #[cfg(feature = "huge-pages")]
+    pub fn huge_pages(mut self) -> Builder<T> {
+        self.huge_pages = true;
+        self
+    }
+
+    //reports every guard on this vec that has been held for longer than limit (once per
+    //guard) along with where it was created
+    #[cfg(feature = "watchdog")]
+    pub fn watchdog<F>(mut self, limit : Duration, callback : F) -> Builder<T> where F : Fn(&HeldGuard) + Send + Sync + 'static {
+        self.watchdog = Some((limit, Arc::new(callback)));
+        self
+    }
+
+    //logs a warning (with the vec's name) whenever a guard or push waits longer than
+    //acquire for its lock or a guard is held for longer than hold...a cheaper stand in
+    //for the watchdog that only needs a logger
+    #[cfg(feature = "log")]
+    pub fn slow_lock_warnings(mut self, acquire : Duration, hold : Duration) -> Builder<T> {
+        self.slow_locks = Some((acquire, hold));
+        self
+    }
+
+    pub fn build(self) -> Arc<RWVec<T>> {
+        Arc::new(RWVec::from_builder(self))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ShrinkPolicy {
+    watermark : f32,
+    after     : Duration
+}
+
+//keeps track of how long the vec has been under the shrink watermark...only ever
+//touched with the push lock held so the timestamp doesn't need its own lock
+struct Shrinker {
+    policy      : Option<ShrinkPolicy>,
+    below_since : UnsafeCell<Option<Instant>>
+}
+
+impl Shrinker {
+    fn new(policy : Option<ShrinkPolicy>) -> Shrinker {
+        Shrinker {
+            policy      : policy,
+            below_since : UnsafeCell::new(None)
+        }
+    }
+
+    //has to be called with the push lock held...returns true once the vec has been
+    //below the watermark for long enough
+    unsafe fn observe<T>(&self, vec : &std::vec::Vec<T>) -> bool {
+        let policy = match self.policy {
+            Some(policy) => policy,
+            None         => return false
+        };
+
+        let below_since = &mut *self.below_since.get();
+
+        if vec.capacity() == 0 || vec.len() as f32 >= vec.capacity() as f32 * policy.watermark {
+            *below_since = None;
+            return false
+        }
+
+        //the clock starts with whichever push or exclusive section first finds it below,
+        //so a drain with nothing after it still counts
+        let since = *below_since.get_or_insert_with(Instant::now);
+        since.elapsed() >= policy.after
+    }
+
+    //has to be called with exclusive access (push lock and write lock)
+    unsafe fn shrink_if_due<T>(&self, vec : &mut std::vec::Vec<T>) {
+        if self.observe(vec) {
+            let target = vec.len() * 2;
+            shrink_wiping(vec, target);
+            *self.below_since.get() = None;
+        }
+    }
+}
+
+//which way the fill level went
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Watermark {
+    High,
+    Low
+}
+
+//what byte_budget() keeps track of...used is an estimate, it is only as good as
+//size_of and changes made through get_mut aren't seen until the next exclusive access
+struct Budget<T> {
+    limit   : usize,
+    size_of : fn(&T) -> usize,
+    used    : AtomicUsize
+}
+
+impl<T> Budget<T> {
+    fn measure(&self, elements : &[T]) -> usize {
+        elements.iter().map(|t| (self.size_of)(t)).sum()
+    }
+
+    //elements weighed before when last measured
+    fn settle(&self, before : usize, elements : &[T]) {
+        let after = self.measure(elements);
+        if after >= before {
+            self.used.fetch_add(after - before, Ordering::SeqCst);
+        } else {
+            self.used.fetch_sub(before - after, Ordering::SeqCst);
+        }
+    }
+
+    fn over(&self) -> bool {
+        self.used.load(Ordering::SeqCst) > self.limit
+    }
+}
+
+struct Watermarks {
+    low      : f32,
+    high     : f32,
+    //between the two marks the last one crossed decides, so a len hovering around
+    //either mark doesn't keep firing
+    above    : AtomicBool,
+    callback : Box<dyn Fn(Watermark) + Send + Sync>
+}
+
+impl Watermarks {
+    fn check(&self, len : usize, limit : usize) {
+        if limit == 0 {
+            return
+        }
+
+        let fill = len as f32 / limit as f32;
+
+        if fill >= self.high {
+            if !self.above.swap(true, Ordering::SeqCst) {
+                (self.callback)(Watermark::High);
+            }
+        } else if fill <= self.low {
+            if self.above.swap(false, Ordering::SeqCst) {
+                (self.callback)(Watermark::Low);
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 LOCKS                                     //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//how many reader counters each lock spreads its readers over...threads are dealt out
+//round robin so with up to this many reader threads nobody shares a counter
+const READER_SLOTS : usize = 64;
+
+//one reader counter on a cache line of its own so readers on different slots never
+//bounce the same line around
+#[repr(align(64))]
+struct ReaderSlot {
+    count : AtomicUsize
+}
+
+const EMPTY_READER_SLOT : ReaderSlot = ReaderSlot { count : AtomicUsize::new(0) };
+
+//a field on a cache line of its own...the push lock, the writer flag and the push
+//counters each get one so pushers and readers don't keep stealing lines from each
+//other (see the layout test at the bottom)
+#[repr(align(64))]
+struct CachePadded<T> {
+    value : T
+}
+
+impl<T> CachePadded<T> {
+    const fn new(value : T) -> CachePadded<T> {
+        CachePadded { value : value }
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+//the slot this thread reads through, and which locks it currently holds reads on
+thread_local!(static READER : Arc<HeldReads> = Arc::new(HeldReads::new(
+    NEXT_READER_SLOT.fetch_add(1, Ordering::Relaxed) % READER_SLOTS
+)));
+
+static NEXT_READER_SLOT : AtomicUsize = AtomicUsize::new(0);
+
+//how many entries a thread gets for the locks it holds reads on...a thread holding
+//reads on more locks than that chains on another batch, so a nested read is always
+//seen as one
+const HELD_READS : usize = 8;
+
+//one thread's read locks, by lock...only that thread claims entries, but a guard sent
+//elsewhere gives its count back from wherever it is dropped, since its ReadSlot keeps
+//hold of these
+struct HeldReads {
+    slot  : usize,
+    locks : HeldBatch
+}
+
+//entries never move once handed out, so counts can be given back without a lock
+struct HeldBatch {
+    entries : [HeldRead; HELD_READS],
+    next    : std::sync::OnceLock<Box<HeldBatch>>
+}
+
+struct HeldRead {
+    lock  : AtomicUsize,
+    count : AtomicUsize
+}
+
+const NO_HELD_READ : HeldRead = HeldRead { lock : AtomicUsize::new(0), count : AtomicUsize::new(0) };
+
+impl HeldReads {
+    fn new(slot : usize) -> HeldReads {
+        HeldReads {
+            slot  : slot,
+            locks : HeldBatch::new()
+        }
+    }
+
+    fn held(&self, entry : usize) -> &HeldRead {
+        let mut batch = &self.locks;
+        for _ in 0..entry / HELD_READS {
+            batch = batch.next.get().expect("entries are only handed out of batches that exist");
+        }
+
+        &batch.entries[entry % HELD_READS]
+    }
+
+    //the entry counting this thread's reads on lock, claiming a free one if there
+    //are none yet (and another batch if every one is taken)
+    fn entry(&self, lock : &ResizeLock) -> usize {
+        let lock      = lock as *const ResizeLock as usize;
+        let mut free  = None;
+        let mut batch = &self.locks;
+        let mut base  = 0;
+
+        loop {
+            for (i, held) in batch.entries.iter().enumerate() {
+                if held.count.load(Ordering::SeqCst) == 0 {
+                    free = free.or(Some(base + i));
+                } else if held.lock.load(Ordering::Relaxed) == lock {
+                    return base + i
+                }
+            }
+
+            base += HELD_READS;
+            batch = match batch.next.get() {
+                Some(next) => next,
+                None       => break
+            };
+        }
+
+        let entry = match free {
+            Some(entry) => entry,
+            None        => {
+                //only this thread ever claims entries, so nobody else is adding a batch
+                batch.next.get_or_init(|| Box::new(HeldBatch::new()));
+                base
+            }
+        };
+
+        self.held(entry).lock.store(lock, Ordering::Relaxed);
+        entry
+    }
+}
+
+impl HeldBatch {
+    fn new() -> HeldBatch {
+        HeldBatch {
+            entries : [NO_HELD_READ; HELD_READS],
+            next    : std::sync::OnceLock::new()
+        }
+    }
+}
+
+//what a read lock was taken through: the reader counter it bumped, and the entry of
+//the thread that took it
+struct ReadSlot {
+    slot : usize,
+    held : Option<(Arc<HeldReads>, usize)>
+}
+
+impl ReadSlot {
+    //for zero sized elements, which never take the lock
+    fn none() -> ReadSlot {
+        ReadSlot { slot : 0, held : None }
+    }
+}
+
+//how threads wait for the vec's locks
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LockBackend {
+    //sleep until the holder wakes us...the right thing when the locks are held for any
+    //real amount of time or there are more threads than cores
+    Blocking,
+    //burn the core until the lock frees up...only for tiny critical sections on cores
+    //that have nothing better to do
+    Spin,
+    //spin for up to rounds rounds, pausing twice as long each round (but never more
+    //than max_pause spins), then sleep like Blocking. short holds get picked up with
+    //spinning latency, long ones don't eat a core
+    Adaptive { rounds : u32, max_pause : u32 },
+    //spelled out by hand, for when none of the above fit the machine
+    Custom(Backoff)
+}
+
+//how a Custom backend waits: spin like Adaptive, then give the core away yields times,
+//then either sleep like Blocking or (park false) go on spinning until the lock frees
+//up. pinned cores want yields 0 and park false, hosts shared with other work want
+//rounds 0 and yields 0, which sleeps straight away
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Backoff {
+    pub rounds    : u32,
+    pub max_pause : u32,
+    pub yields    : u32,
+    pub park      : bool
+}
+
+impl Backoff {
+    //everything before parking...true if done() came true on the way
+    fn spin_then_yield<F>(&self, done : &mut F) -> bool where F : FnMut() -> bool {
+        if LockBackend::spin_rounds(self.rounds, self.max_pause, done) {
+            return true
+        }
+
+        for _ in 0..self.yields {
+            std::thread::yield_now();
+            if done() {
+                return true
+            }
+        }
+
+        false
+    }
+}
+
+impl LockBackend {
+    //Adaptive with settings that cover critical sections of a few microseconds
+    pub fn adaptive() -> LockBackend {
+        LockBackend::Adaptive { rounds : 10, max_pause : 64 }
+    }
+
+    //the spinning part of Adaptive...true if done() came true before the rounds ran out
+    fn spin_rounds<F>(rounds : u32, max_pause : u32, done : &mut F) -> bool where F : FnMut() -> bool {
+        let mut pause = 1;
+
+        for _ in 0..rounds {
+            if done() {
+                return true
+            }
+
+            for _ in 0..pause {
+                std::hint::spin_loop();
+            }
+            pause = std::cmp::min(pause * 2, max_pause);
+        }
+
+        done()
+    }
+}
+
+//the lock pushers (and anything else that needs the tail to hold still) take...one
+//word living right in the vec, so taking it when nobody else has it is a single
+//compare and swap with no pointer to chase. state is 0 when free, 1 when held and 2
+//when held with somebody asleep waiting for it, so unlock only has to go near parked
+//when there is someone to wake
+struct PushLock {
+    state  : AtomicUsize,
+    parked : Signal
+}
+
+impl PushLock {
+    fn new() -> PushLock {
+        PushLock {
+            state  : AtomicUsize::new(0),
+            parked : Signal::new()
+        }
+    }
+
+    fn try_lock(&self) -> bool {
+        self.state.compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    }
+
+    //sleeps until the lock is free...spinning first is up to the backend
+    fn lock(&self) {
+        if self.try_lock() {
+            return
+        }
+
+        //whoever gets it this way can't know whether anyone else is still waiting, so
+        //it always leaves 2 behind and the next unlock wakes the rest to check
+        self.parked.wait_until(|| self.state.swap(2, Ordering::Acquire) == 0);
+    }
+
+    //waits for the lock the way backend says to
+    fn acquire(&self, backend : LockBackend) {
+        match backend {
+            LockBackend::Blocking => self.lock(),
+            LockBackend::Spin     => {
+                while !self.try_lock() {
+                    std::hint::spin_loop();
+                }
+            },
+            LockBackend::Adaptive { rounds, max_pause } => {
+                if !LockBackend::spin_rounds(rounds, max_pause, &mut || self.try_lock()) {
+                    self.lock();
+                }
+            },
+            LockBackend::Custom(backoff) => {
+                if !backoff.spin_then_yield(&mut || self.try_lock()) {
+                    if backoff.park {
+                        self.lock();
+                    } else {
+                        while !self.try_lock() {
+                            std::hint::spin_loop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    //only for whoever holds the lock
+    unsafe fn unlock(&self) {
+        if self.state.swap(0, Ordering::Release) == 2 {
+            self.parked.notify();
+        }
+    }
+}
+
+//the lock that keeps the buffer from moving out from under readers...it is a
+//big-reader lock: each reader only touches the counter of its own slot and writers
+//pay for it by checking all of them. it also counts how many times the write lock
+//was handed out so anyone working off to the side can tell if the contents might
+//have changed under them
+pub struct ResizeLock {
+    readers    : [ReaderSlot; READER_SLOTS],
+    //set while a writer holds the lock or is waiting for readers to drain...every
+    //reader looks at it, so it gets a line nobody writes to on every read
+    writer     : CachePadded<AtomicBool>,
+    //set once the writer has seen the readers drain and gone in. nested readers only
+    //look at it when writer is set
+    entered    : AtomicBool,
+    //held by the one upgradable reader for as long as it lives, and by every writer
+    //on its way in...so a writer can never be sitting on the writer flag waiting for
+    //the upgradable reader's read lock while that reader waits to upgrade
+    upgrader   : AtomicBool,
+    //readers and writers that had to back off sleep here
+    parked     : Signal,
+    generation : AtomicUsize,
+    //how anyone who has to wait for this lock (or the push lock) does it
+    backend    : LockBackend
+}
+
+impl ResizeLock {
+    pub fn new(backend : LockBackend) -> ResizeLock {
+        ResizeLock {
+            readers    : [EMPTY_READER_SLOT; READER_SLOTS],
+            writer     : CachePadded::new(AtomicBool::new(false)),
+            entered    : AtomicBool::new(false),
+            upgrader   : AtomicBool::new(false),
+            parked     : Signal::new(),
+            generation : AtomicUsize::new(0),
+            backend    : backend
+        }
+    }
+
+    fn wait_until<F>(&self, mut done : F) where F : FnMut() -> bool {
+        match self.backend {
+            LockBackend::Blocking => self.parked.wait_until(done),
+            LockBackend::Spin     => {
+                while !done() {
+                    std::hint::spin_loop();
+                }
+            },
+            LockBackend::Adaptive { rounds, max_pause } => {
+                if !LockBackend::spin_rounds(rounds, max_pause, &mut done) {
+                    self.parked.wait_until(done);
+                }
+            },
+            LockBackend::Custom(backoff) => {
+                if !backoff.spin_then_yield(&mut done) {
+                    if backoff.park {
+                        self.parked.wait_until(done);
+                    } else {
+                        while !done() {
+                            std::hint::spin_loop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    //false if done() still wasn't true by deadline...it always gets at least one look
+    fn wait_until_deadline<F>(&self, deadline : Instant, mut done : F) -> bool where F : FnMut() -> bool {
+        match self.backend {
+            LockBackend::Blocking | LockBackend::Adaptive { .. } | LockBackend::Custom(Backoff { park : true, .. }) => {
+                match self.backend {
+                    LockBackend::Adaptive { rounds, max_pause } => {
+                        if LockBackend::spin_rounds(rounds, max_pause, &mut done) {
+                            return true
+                        }
+                    },
+                    LockBackend::Custom(backoff) => {
+                        if backoff.spin_then_yield(&mut done) {
+                            return true
+                        }
+                    },
+                    _ => ()
+                }
+
+                let now = Instant::now();
+                if now >= deadline {
+                    return done()
+                }
+
+                self.parked.wait_until_timeout(deadline - now, done)
+            },
+            LockBackend::Spin | LockBackend::Custom(Backoff { park : false, .. }) => {
+                loop {
+                    if done() {
+                        return true
+                    }
+
+                    if Instant::now() >= deadline {
+                        return false
+                    }
+
+                    std::hint::spin_loop();
+                }
+            }
+        }
+    }
+
+    fn drained(&self) -> bool {
+        self.readers.iter().all(|slot| slot.count.load(Ordering::SeqCst) == 0)
+    }
+
+    //returns what the reader has to be released through
+    unsafe fn read(&self) -> ReadSlot {
+        let (reads, entry) = READER.with(|reader| (reader.clone(), reader.entry(self)));
+
+        loop {
+            self.readers[reads.slot].count.fetch_add(1, Ordering::SeqCst);
+
+            if self.may_enter(&reads, entry) {
+                return self.entered_through(reads, entry)
+            }
+
+            //a writer is in or waiting...get out of its way until it's done
+            self.read_unlock_slot(reads.slot);
+            self.wait_until(|| self.may_enter(&reads, entry));
+        }
+    }
+
+    //read() that gives up once deadline has passed
+    unsafe fn read_until(&self, deadline : Instant) -> Option<ReadSlot> {
+        let (reads, entry) = READER.with(|reader| (reader.clone(), reader.entry(self)));
+
+        loop {
+            self.readers[reads.slot].count.fetch_add(1, Ordering::SeqCst);
+
+            if self.may_enter(&reads, entry) {
+                return Some(self.entered_through(reads, entry))
+            }
+
+            self.read_unlock_slot(reads.slot);
+
+            if !self.wait_until_deadline(deadline, || self.may_enter(&reads, entry)) {
+                return None
+            }
+        }
+    }
+
+    //whether a reader whose counter is already bumped can stay. a thread that already
+    //holds a read lock on this lock can't back off for a writer that is still waiting
+    //for readers to drain, the writer would be waiting on that first read forever...so
+    //it goes past a waiting writer, but never one that has gone in (see drain)
+    fn may_enter(&self, reads : &HeldReads, entry : usize) -> bool {
+        if !self.writer.load(Ordering::SeqCst) {
+            return true
+        }
+
+        reads.held(entry).count.load(Ordering::SeqCst) > 0 && !self.entered.load(Ordering::SeqCst)
+    }
+
+    fn entered_through(&self, reads : Arc<HeldReads>, entry : usize) -> ReadSlot {
+        reads.held(entry).count.fetch_add(1, Ordering::SeqCst);
+
+        ReadSlot {
+            slot : reads.slot,
+            held : Some((reads, entry))
+        }
+    }
+
+    //one look, no waiting...unlike read() it doesn't count on the caller's other read
+    //locks to keep writers out, since those may be on other vecs
+    unsafe fn try_read_now(&self) -> Option<ReadSlot> {
+        let (reads, entry) = READER.with(|reader| (reader.clone(), reader.entry(self)));
+        self.readers[reads.slot].count.fetch_add(1, Ordering::SeqCst);
+
+        if !self.writer.load(Ordering::SeqCst) {
+            return Some(self.entered_through(reads, entry))
+        }
+
+        self.read_unlock_slot(reads.slot);
+        None
+    }
+
+    fn wait_for_writer(&self) {
+        self.wait_until(|| !self.writer.load(Ordering::SeqCst));
+    }
+
+    //another read through a read lock the caller already holds...no writer can be in,
+    //so there's nothing to back off from
+    unsafe fn read_again(&self, read : &ReadSlot) -> ReadSlot {
+        self.readers[read.slot].count.fetch_add(1, Ordering::SeqCst);
+        if let Some((ref reads, i)) = read.held {
+            reads.held(i).count.fetch_add(1, Ordering::SeqCst);
+        }
+
+        ReadSlot {
+            slot : read.slot,
+            held : read.held.clone()
+        }
+    }
+
+    //fine from any thread, the count goes back to the thread that took the lock
+    unsafe fn read_unlock(&self, read : &ReadSlot) {
+        if let Some((ref reads, i)) = read.held {
+            reads.held(i).count.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.read_unlock_slot(read.slot);
+    }
+
+    fn read_unlock_slot(&self, slot : usize) {
+        self.readers[slot].count.fetch_sub(1, Ordering::SeqCst);
+
+        //the writer might be waiting on exactly this counter
+        if self.writer.load(Ordering::SeqCst) {
+            self.parked.notify();
+        }
+    }
+
+    unsafe fn write(&self) {
+        //one writer (or upgradable reader) at a time
+        self.enter_upgradable();
+        self.writer.store(true, Ordering::SeqCst);
+
+        //new readers back off now, wait for the ones already in to leave
+        self.drain();
+    }
+
+    //waits for the readers to leave and goes in. nested readers may still come in
+    //while the writer is only waiting, so after raising entered the counters get one
+    //more look...a reader that bumped its counter before entered went up is seen
+    //there and the writer steps back out to wait for it, one that bumped it after sees
+    //entered and backs off itself
+    fn drain(&self) {
+        loop {
+            self.wait_until(|| self.drained());
+
+            if self.try_enter() {
+                return
+            }
+        }
+    }
+
+    fn try_enter(&self) -> bool {
+        self.entered.store(true, Ordering::SeqCst);
+        if self.drained() {
+            return true
+        }
+
+        self.entered.store(false, Ordering::SeqCst);
+        self.parked.notify();
+        false
+    }
+
+    //write() that gives up once deadline has passed
+    unsafe fn write_until(&self, deadline : Instant) -> bool {
+        while self.upgrader.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            if !self.wait_until_deadline(deadline, || !self.upgrader.load(Ordering::SeqCst)) {
+                return false
+            }
+        }
+
+        self.writer.store(true, Ordering::SeqCst);
+        while self.wait_until_deadline(deadline, || self.drained()) {
+            if self.try_enter() {
+                return true
+            }
+        }
+
+        //never got in...let the readers that backed off have their turn again. nothing
+        //was touched so the generation stays put
+        self.writer.store(false, Ordering::SeqCst);
+        self.upgrader.store(false, Ordering::SeqCst);
+        self.parked.notify();
+        false
+    }
+
+    unsafe fn write_unlock(&self) {
+        //whoever held this could have touched anything
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.entered.store(false, Ordering::SeqCst);
+        self.writer.store(false, Ordering::SeqCst);
+        self.upgrader.store(false, Ordering::SeqCst);
+        self.parked.notify();
+    }
+
+    //shuts out writers and other upgradable readers, but not plain readers
+    unsafe fn enter_upgradable(&self) {
+        while self.upgrader.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            self.wait_until(|| !self.upgrader.load(Ordering::SeqCst));
+        }
+    }
+
+    unsafe fn leave_upgradable(&self) {
+        self.upgrader.store(false, Ordering::SeqCst);
+        self.parked.notify();
+    }
+
+    //the upgradable reader becoming the writer...new readers back off from here on. the
+    //caller lets go of its own read lock in between, then finish_upgrade waits out the
+    //readers that were already in. write_unlock lets go of everything afterwards
+    unsafe fn begin_upgrade(&self) {
+        self.writer.store(true, Ordering::SeqCst);
+    }
+
+    unsafe fn finish_upgrade(&self) {
+        self.drain();
+    }
+
+    fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+}
+
+//somewhere for threads (and tasks) to sleep until something about the vec changes...
+//anyone changing the condition has to do it before calling notify. notify is just an
+//atomic load when nobody is waiting, so it is cheap enough for the push path
+struct Signal {
+    //sleeping threads plus registered wakers...bumped before the condition is checked
+    //so a notify that sees zero can't have missed anyone
+    waiting : AtomicUsize,
+    wakers  : Mutex<std::vec::Vec<Waker>>,
+    cond    : Condvar
+}
+
+impl Signal {
+    fn new() -> Signal {
+        Signal {
+            waiting : AtomicUsize::new(0),
+            wakers  : Mutex::new(std::vec::Vec::new()),
+            cond    : Condvar::new()
+        }
+    }
+
+    fn wait_until<F>(&self, mut done : F) where F : FnMut() -> bool {
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+
+        let mut wakers = self.wakers.lock().unwrap();
+        while !done() {
+            wakers = self.cond.wait(wakers).unwrap();
+        }
+
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    //returns false if the deadline passed before done() came true
+    fn wait_until_timeout<F>(&self, timeout : Duration, mut done : F) -> bool where F : FnMut() -> bool {
+        let deadline = Instant::now() + timeout;
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+
+        let mut wakers = self.wakers.lock().unwrap();
+        let mut result = true;
+
+        while !done() {
+            let now = Instant::now();
+            if now >= deadline {
+                result = false;
+                break
+            }
+            wakers = self.cond.wait_timeout(wakers, deadline - now).unwrap().0;
+        }
+
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    //the async flavour of wait_until...registers the waker before checking so a
+    //notify can't slip in between
+    fn poll_until<F>(&self, cx : &mut Context, done : F) -> Poll<()> where F : FnOnce() -> bool {
+        let mut wakers = self.wakers.lock().unwrap();
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+
+        if done() {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            return Poll::Ready(())
+        }
+
+        if wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            //already registered and counted from an earlier poll
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+        } else {
+            wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+
+    fn notify(&self) {
+        if self.waiting.load(Ordering::SeqCst) == 0 {
+            return
+        }
+
+        let wakers = mem::take(&mut *self.wakers.lock().unwrap());
+        self.waiting.fetch_sub(wakers.len(), Ordering::SeqCst);
+        self.cond.notify_all();
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                             IMMUTABLE GUARD                               //                               
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//the guards of a leaked vec, which can be kept around for as long as they are needed
+pub type StaticSliceGuard<T>    = SliceGuard<'static, T>;
+pub type StaticSliceGuardMut<T> = SliceGuardMut<'static, T>;
+
+//multiple read access to a slice representing the current
+//state of the Vec...pushers can still push on the vec as long as they don't 
+//need to reallocate
+pub struct SliceGuard<'locked, T : 'locked> {
+    //the vec this is a view into...the locks live there too
+    owner  : &'locked RWVec<T>,
+    //how far to slice on deref...pushers may have corrupted past here
+    end    : usize,
+    //the epoch slot this reader is counted in for wait_for_readers
+    slot   : usize,
+    //what this guard holds the read lock through
+    read   : ReadSlot,
+    //lets the watchdog know this guard exists
+    #[allow(dead_code)]
+    ticket : GuardTicket<'locked>
+}   
+
+impl<'locked, T> SliceGuard<'locked, T> {
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn new(owner : &'locked RWVec<T>) -> SliceGuard<'locked, T> {
+        //zero sized elements never move so there is nothing to lock out
+        #[cfg(feature = "log")]
+        let asked = Instant::now();
+
+        let read = if !RWVec::<T>::is_zst() {
+            unsafe { owner.rw_lock.read() }
+        } else {
+            ReadSlot::none()
+        };
+
+        #[cfg(feature = "log")]
+        owner.waited("read", asked);
+
+        SliceGuard::locked(owner, read)
+    }
+
+    //for a read lock the caller already took through read
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn locked(owner : &'locked RWVec<T>, read : ReadSlot) -> SliceGuard<'locked, T> {
+        SliceGuard {
+            owner  : owner,
+            end    : owner.visible_len(),
+            slot   : owner.epochs.enter(),
+            read   : read,
+            ticket : GuardTicket::new(owner, "read")
+        }   
+    }
+
+    //cuts the view down to the first end elements, for guards that have to line up
+    //with others (the columns of rwvec_columns!)
+    pub fn clipped(mut self, end : usize) -> SliceGuard<'locked, T> {
+        self.end = std::cmp::min(self.end, end);
+        self
+    }
+
+    //keeps the read lock but only hands out what f picks out of element index, e.g.
+    //guard.map_element(i, |t| &t.name) derefs to the name
+    pub fn map_element<U : ?Sized, F>(self, index : usize, f : F) -> MappedGuard<'locked, T, U> where F : FnOnce(&T) -> &U {
+        if index >= self.len() {
+            panic!("{}: index {} out of bounds (len {})", self.owner.label(), index, self.len());
+        }
+
+        let target = f(&self[index]) as *const U;
+        MappedGuard { guard : self, target : target }
+    }
+
+    //this updates your view of the vec by yielding and then acquiring both locks
+    pub fn refresh(&mut self) { 
+        self.owner.counters.refreshes.fetch_add(1, Ordering::Relaxed);
+
+        if RWVec::<T>::is_zst() {
+            self.end = self.owner.visible_len();
+            return
+        }
+
+        unsafe {
+            //give the pending reallocating pushers a chance to finish so no deadlock
+            self.owner.rw_lock.read_unlock(&self.read); 
+            //seal off the pushers
+            self.owner.acquire_push_lock();
+            //register yourself as a reader again
+            self.read = self.owner.rw_lock.read(); 
+        }
+
+        self.end = self.owner.visible_len();
+
+        unsafe {
+            //let non-reallocating pushers in again
+            self.owner.push_lock.unlock();
+        } 
+    }
+}
+
+//a second view of exactly the same snapshot...it holds its own read lock so the two
+//can be handed to different components and dropped independently
+impl<'locked, T> Clone for SliceGuard<'locked, T> {
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn clone(&self) -> SliceGuard<'locked, T> {
+        let read = if !RWVec::<T>::is_zst() {
+            unsafe { self.owner.rw_lock.read_again(&self.read) }
+        } else {
+            ReadSlot::none()
+        };
+
+        SliceGuard {
+            owner  : self.owner,
+            end    : self.end,
+            slot   : self.owner.epochs.enter(),
+            read   : read,
+            ticket : GuardTicket::new(self.owner, "read")
+        }
+    }
+}
+
+impl<'locked, T> IntoIterator for &'locked SliceGuard<'locked, T> {
+    type Item     = &'locked T;
+    type IntoIter = std::slice::Iter<'locked, T>;
+
+    fn into_iter(self) -> std::slice::Iter<'locked, T> {
+        //the deref on the functin call delegates this to the slice
+        self.iter()
+    }
+}
+
+impl<'locked, T> Deref for SliceGuard<'locked, T> {
+    type Target = [T];
+
+    fn deref<'a>(&'a self) -> &'a [T] {
+        unsafe { std::slice::from_raw_parts(self.owner.vec().as_ptr(), self.end) }
+    }
+}
+
+impl<'locked, T> Drop for SliceGuard<'locked, T> { 
+    fn drop(&mut self) {
+        if !RWVec::<T>::is_zst() {
+            unsafe { self.owner.rw_lock.read_unlock(&self.read); }
+        }
+
+        self.owner.epochs.leave(self.slot);
+    }
+}
+
+//a read guard that is the only one able to upgrade...see RWVec::upgradable
+pub struct UpgradableGuard<'locked, T : 'locked> {
+    reader : SliceGuard<'locked, T>
+}
+
+impl<'locked, T> UpgradableGuard<'locked, T> {
+    //the same elements, now writable...no writer can have been in since this was made.
+    //waits for the plain readers to leave like writer() would
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn upgrade(self) -> SliceGuardMut<'locked, T> {
+        let owner  = self.reader.owner;
+        //the gate stays taken, the writer guard releases it with the write lock
+        let reader = unsafe { ptr::read(&self.reader) };
+        mem::forget(self);
+
+        unsafe {
+            owner.rw_lock.begin_upgrade();
+            drop(reader);
+            owner.rw_lock.finish_upgrade();
+        }
+
+        SliceGuardMut::locked(owner)
+    }
+}
+
+impl<'locked, T> Deref for UpgradableGuard<'locked, T> {
+    type Target = [T];
+
+    fn deref<'a>(&'a self) -> &'a [T] {
+        &self.reader
+    }
+}
+
+impl<'locked, T> Drop for UpgradableGuard<'locked, T> {
+    fn drop(&mut self) {
+        unsafe { self.reader.owner.rw_lock.leave_upgradable(); }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                             MUTABLE GUARDS                                //                               
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//Exlusive read and write access to a slice representing the current
+//state of the Vec...pushers can still push on the vec as long as they don't 
+//need to reallocate
+pub struct SliceGuardMut<'locked, T : 'locked> {
+    //the vec this is a view into...the locks live there too
+    owner  : &'locked RWVec<T>,
+    //how far to slice on deref...pushers may have corrupted past here
+    end      : usize,
+    //where end was before the last refresh, everything from here on is new
+    fresh    : usize,
+    //what the elements weighed for the byte budget when the write lock was taken,
+    //None once an upgrade has taken over accounting for them
+    budgeted : Cell<Option<usize>>,
+    //lets the watchdog know this guard exists
+    #[allow(dead_code)]
+    ticket   : GuardTicket<'locked>
+}   
+
+impl<'locked, T> SliceGuardMut<'locked, T> {
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn new(owner : &'locked RWVec<T>) -> SliceGuardMut<'locked, T> {
+        #[cfg(feature = "log")]
+        let asked = Instant::now();
+
+        unsafe { owner.rw_lock.write() }
+
+        #[cfg(feature = "log")]
+        owner.waited("write", asked);
+
+        SliceGuardMut::locked(owner)
+    }
+
+    //for a write lock the caller already took
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn locked(owner : &'locked RWVec<T>) -> SliceGuardMut<'locked, T> {
+        let end = owner.visible_len();
+
+        SliceGuardMut {
+            //the vec this is a view into...the locks live there too
+            owner    : owner,
+            //how far to slice on deref...pushers may have corrupted past here
+            end      : end,
+            fresh    : end,
+            budgeted : Cell::new(Some(owner.budgeted(unsafe { &owner.vec()[..end] }))),
+            //lets the watchdog know this guard exists
+            ticket   : GuardTicket::new(owner, "write")
+        }   
+    }
+
+    //just the elements the last refresh brought into view, e.g. to finish setting up
+    //records that were pushed half initialized...empty before any refresh
+    pub fn new_mut(&mut self) -> &mut [T] {
+        let fresh = self.fresh;
+        &mut self[fresh..]
+    }
+
+    pub fn iter_new_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.new_mut().iter_mut()
+    }
+
+    //squares the byte budget up with whatever was written through this guard...has to
+    //happen before the write lock is let go
+    fn settle_budget(&self) {
+        if let Some(before) = self.budgeted.take() {
+            self.owner.rebudget(before, &self[..]);
+        }
+    }
+
+    //this updates your view of the vec by yielding and then acquiring both locks
+    pub fn refresh(&mut self) { 
+        self.owner.counters.refreshes.fetch_add(1, Ordering::Relaxed);
+
+        self.settle_budget();
+
+        unsafe {
+            //release pushers waiting to realloc
+            self.owner.rw_lock.write_unlock();
+
+            //seal off pushers
+            self.owner.acquire_push_lock();
+
+            //wait for immutable readers to be dropped then lock out new ones
+            self.owner.rw_lock.write();
+        }
+
+        //an exclusive section in between could have taken elements off
+        let end    = self.owner.visible_len();
+        self.fresh = std::cmp::min(self.end, end);
+        self.end   = end;
+        self.budgeted.set(Some(self.owner.budgeted(&self[..])));
+
+        unsafe {
+            //let non-reallocating pushers in again
+            self.owner.push_lock.unlock();
+        } 
+    }
+
+    //this acquires the push lock as well so you have exclusive access
+    //this is basically a scoped version of refresh that lets you exclusively mutate the whole vec 
+    //until the guard drops
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn upgrade(&self) -> VecGuardMut<'_, T> { 
+        self.owner.counters.upgrades.fetch_add(1, Ordering::Relaxed);
+        log_event!(self.owner, Event::Upgrade);
+        //the upgrade weighs the whole vec again when it drops
+        self.settle_budget();
+
+        unsafe {
+            //give the pending reallocating pushers a chance to finish so no deadlock
+            self.owner.rw_lock.write_unlock(); 
+            //seal off the pushers by creating a vec guard
+            let vec_guard = VecGuardMut::new(self.owner);
+            //seal off any other reader
+            self.owner.rw_lock.write(); 
+
+            vec_guard
+        }
+    }
+}
+
+impl<'locked, T> IntoIterator for &'locked SliceGuardMut<'locked, T> {
+    type Item     = &'locked T;
+    type IntoIter = std::slice::Iter<'locked, T>;
+
+    fn into_iter(self) -> std::slice::Iter<'locked, T> {
+        //the deref on the functin call delegates this to the slice
+        self.iter()
+    }
+}
+
+impl<'locked, T> IntoIterator for &'locked mut SliceGuardMut<'locked, T> {
+    type Item     = &'locked mut T;
+    type IntoIter = std::slice::IterMut<'locked, T>;
+
+    fn into_iter(self) -> std::slice::IterMut<'locked, T> {
+        //the deref on the functin call delegates this to the slice
+        self.iter_mut()
+    }
+}
+
+impl<'locked, T> Deref for SliceGuardMut<'locked, T> {
+    type Target = [T];
+
+    fn deref<'a>(&'a self) -> &'a [T] {
+        unsafe { std::slice::from_raw_parts(self.owner.vec().as_ptr(), self.end) }
+    }
+}
+
+impl<'locked, T> DerefMut for SliceGuardMut<'locked, T> {
+    fn deref_mut<'a>(&'a mut self) -> &'a mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.owner.vec().as_mut_ptr(), self.end) }
+    }
+}
+
+impl<'locked, T> Drop for SliceGuardMut<'locked, T> { 
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.owner.poisoned.store(true, Ordering::SeqCst);
+        }
+
+        self.settle_budget();
+
+        //any of the elements we had could have been written to
+        if self.end != 0 {
+            self.owner.announce(ChangeEvent::Replaced { range : 0..self.end });
+        }
+
+        unsafe { self.owner.rw_lock.write_unlock(); }
+    }
+}
+
+//Exclusive read and write acces to the whole vec...pushers get blocked while
+//they wait for this to drop
+pub struct VecGuardMut<'locked, T : 'locked> {
+    //exclusive access to the vec...unlock its push lock on drop
+    owner   : &'locked RWVec<T>,
+    //zero sized pushes only bump a count, this is where the count was when we
+    //copied it into the scratch vec
+    base    : usize,
+    //zero sized elements get a vec of their own to play with, so the real one keeps
+    //the len unlocked readers add to the count
+    scratch : std::vec::Vec<T>,
+    //the section only took elements off the end, so subscribers can be told exactly
+    //that instead of everything being replaced
+    tail_only : bool,
+    //lets the watchdog know this guard exists
+    #[allow(dead_code)]
+    ticket  : GuardTicket<'locked>
+}
+
+impl<'locked, T> VecGuardMut<'locked, T> {
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    fn new(owner : &'locked RWVec<T>) -> VecGuardMut<'locked, T> {
+        unsafe { owner.acquire_push_lock() }
+
+        //give zero sized elements a vec with a real len so it can be truncated, popped
+        //etc like any other
+        let base        = owner.visible_len();
+        let mut scratch = std::vec::Vec::new();
+        if RWVec::<T>::is_zst() {
+            unsafe { scratch.set_len(base); }
+        }
+
+        VecGuardMut {
+            owner     : owner,
+            base      : base,
+            scratch   : scratch,
+            tail_only : false,
+            ticket    : GuardTicket::new(owner, "exclusive")
+        }
+    }
+
+    //tells subscribers what this guard did, before the push lock goes so no push can
+    //get announced ahead of it
+    fn announce_changes(&self) {
+        let (before, after) = (self.base, self.owner.visible_len());
+        if before == after && self.tail_only {
+            return
+        }
+
+        if after == 0 {
+            if before != 0 {
+                self.owner.announce(ChangeEvent::Cleared);
+            }
+        } else if self.tail_only {
+            self.owner.announce(ChangeEvent::Truncated { len : after });
+        } else {
+            //no telling which elements got touched in here
+            self.owner.announce(ChangeEvent::Replaced { range : 0..std::cmp::min(before, after) });
+
+            if after < before {
+                self.owner.announce(ChangeEvent::Truncated { len : after });
+            } else if after > before {
+                self.owner.announce(ChangeEvent::Appended { range : before..after });
+            }
+        }
+    }
+}
+
+impl<'locked, T> IntoIterator for &'locked VecGuardMut<'locked, T> {
+    type Item     = &'locked T;
+    type IntoIter = std::slice::Iter<'locked, T>;
+
+    fn into_iter(self) -> std::slice::Iter<'locked, T> {
+        //the deref on the functin call delegates this to the vec
+        self.iter()
+    }
+}
+
+impl<'locked, T> IntoIterator for &'locked mut VecGuardMut<'locked, T> {
+    type Item     = &'locked mut T;
+    type IntoIter = std::slice::IterMut<'locked, T>;
+
+    fn into_iter(self) -> std::slice::IterMut<'locked, T> {
+        //the deref on the functin call delegates this to the vec
+        self.iter_mut()
+    }
+}
+
+impl<'locked, T> Deref for VecGuardMut<'locked, T> {
+    type Target = std::vec::Vec<T>;
+
+    fn deref<'a>(&'a self) -> &'a std::vec::Vec<T> {
+        if RWVec::<T>::is_zst() {
+            return &self.scratch
+        }
+
+        unsafe { self.owner.vec() }
+    }
+}
+
+
+impl<'locked, T> DerefMut for VecGuardMut<'locked, T> {
+    fn deref_mut<'a>(&'a mut self) -> &'a mut std::vec::Vec<T> {
+        if RWVec::<T>::is_zst() {
+            return &mut self.scratch
+        }
+
+        unsafe { self.owner.vec() }
+    }
+}
+
+impl<'locked, T> Drop for VecGuardMut<'locked, T> { 
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.owner.poisoned.store(true, Ordering::SeqCst);
+        }
+
+        unsafe {
+            let vec = self.owner.vec();
+
+            //zero sized pushes kept landing on the count while we held this, so
+            //fold in whatever we added or removed and hand the count back
+            if RWVec::<T>::is_zst() {
+                let len = self.scratch.len();
+                if len >= self.base {
+                    self.owner.zst_len.fetch_add(len - self.base, Ordering::SeqCst);
+                } else {
+                    self.owner.zst_len.fetch_sub(self.base - len, Ordering::SeqCst);
+                }
+                //what's left is accounted for by the count
+                self.scratch.set_len(0);
+            }
+
+            log_event!(self.owner, Event::Exclusive { before : self.base, after : self.owner.visible_len() });
+            //no telling what happened in here, so weigh everything again while no
+            //pusher can add to it
+            if let Some(ref budget) = self.owner.budget {
+                budget.used.store(budget.measure(vec), Ordering::SeqCst);
+            }
+            self.owner.shrinker.shrink_if_due(vec);
+            //whatever happened in here might have moved the buffer
+            self.owner.advise(vec);
+            self.announce_changes();
+            self.owner.push_lock.unlock();
+        }
+
+        //whatever happened to the vec in here, anyone waiting on it should take a look
+        self.owner.contents_changed();
+    }
+}
+
+//read access to a single element, usually one that was just pushed...it holds a
+//regular read guard underneath so the element can't move while this is around
+pub struct ElementGuard<'locked, T : 'locked> {
+    guard : SliceGuard<'locked, T>,
+    index : usize
+}
+
+impl<'locked, T> ElementGuard<'locked, T> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    //narrows the guard down to part of the element, e.g. |t| &t.name
+    pub fn map<U : ?Sized, F>(self, f : F) -> MappedGuard<'locked, T, U> where F : FnOnce(&T) -> &U {
+        let index = self.index;
+        self.guard.map_element(index, f)
+    }
+}
+
+//a read guard that derefs to something inside one of the elements, usually a field...
+//the read lock underneath keeps it from moving, so it can be handed to anything that
+//takes a plain reference without cloning it out first
+pub struct MappedGuard<'locked, T : 'locked, U : ?Sized + 'locked> {
+    //only here to keep the read lock
+    #[allow(dead_code)]
+    guard  : SliceGuard<'locked, T>,
+    target : *const U
+}
+
+impl<'locked, T, U : ?Sized> Deref for MappedGuard<'locked, T, U> {
+    type Target = U;
+
+    fn deref<'a>(&'a self) -> &'a U {
+        //points into an element the guard keeps in place
+        unsafe { &*self.target }
+    }
+}
+
+impl<'locked, T> Deref for ElementGuard<'locked, T> {
+    type Target = T;
+
+    fn deref<'a>(&'a self) -> &'a T {
+        &self.guard[self.index]
+    }
+}
+
+//what the vec's own exclusive operations (take_all and friends) run under...the push
+//lock and the write lock at once. fields drop in order so the vec guard gets to shrink
+//before the write lock goes
+pub struct ExclusiveGuard<'locked, T : 'locked> {
+    vec   : VecGuardMut<'locked, T>,
+    #[allow(dead_code)]
+    write : WriteHold<'locked>
+}
+
+impl<'locked, T> ExclusiveGuard<'locked, T> {
+    //promises the section only takes elements off the end
+    fn tail_only(&mut self) {
+        self.vec.tail_only = true;
+    }
+}
+
+struct WriteHold<'locked> {
+    lock : &'locked ResizeLock
+}
+
+struct PushHold<'locked> {
+    lock : &'locked PushLock
+}
+
+impl<'locked> Drop for PushHold<'locked> {
+    fn drop(&mut self) {
+        unsafe { self.lock.unlock(); }
+    }
+}
+
+impl<'locked> Drop for WriteHold<'locked> {
+    fn drop(&mut self) {
+        unsafe { self.lock.write_unlock(); }
+    }
+}
+
+impl<'locked, T> Deref for ExclusiveGuard<'locked, T> {
+    type Target = std::vec::Vec<T>;
+
+    fn deref<'a>(&'a self) -> &'a std::vec::Vec<T> {
+        &self.vec
+    }
+}
+
+impl<'locked, T> DerefMut for ExclusiveGuard<'locked, T> {
+    fn deref_mut<'a>(&'a mut self) -> &'a mut std::vec::Vec<T> {
+        &mut self.vec
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                QUIESCENCE                                 //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//readers register in one of two slots depending on the epoch they started in...a
+//fence bumps the epoch and then waits for the old slot to empty out. the epoch is only
+//ever bumped once the slot it is about to reuse has drained, so a slot never mixes
+//readers from before a fence with readers from after the one before it
+struct ReaderEpochs {
+    current : AtomicUsize,
+    active  : [AtomicUsize; 2],
+    //only one fence flips the epoch at a time
+    flip    : Mutex<()>,
+    drained : Signal
+}
+
+impl ReaderEpochs {
+    fn new() -> ReaderEpochs {
+        ReaderEpochs {
+            current : AtomicUsize::new(0),
+            active  : [AtomicUsize::new(0), AtomicUsize::new(0)],
+            flip    : Mutex::new(()),
+            drained : Signal::new()
+        }
+    }
+
+    //returns the slot the reader has to leave through
+    fn enter(&self) -> usize {
+        let slot = self.current.load(Ordering::SeqCst) & 1;
+        self.active[slot].fetch_add(1, Ordering::SeqCst);
+        slot
+    }
+
+    fn leave(&self, slot : usize) {
+        if self.active[slot].fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.drained.notify();
+        }
+    }
+
+    //moves everyone currently reading into the past...returns the epoch they are in,
+    //or None if an older fence still has readers in the slot we'd have to reuse
+    fn try_flip(&self) -> Option<usize> {
+        let _flip = self.flip.lock().unwrap();
+        let epoch = self.current.load(Ordering::SeqCst);
+
+        if self.active[(epoch + 1) & 1].load(Ordering::SeqCst) != 0 {
+            return None
+        }
+
+        self.current.store(epoch + 1, Ordering::SeqCst);
+        Some(epoch)
+    }
+
+    fn is_drained(&self, epoch : usize) -> bool {
+        //a later flip only happens once this slot has been empty
+        self.current.load(Ordering::SeqCst) >= epoch + 2 ||
+        self.active[epoch & 1].load(Ordering::SeqCst) == 0
+    }
+
+    //blocks until everyone who entered before the call has left
+    fn quiesce(&self) {
+        let mut epoch = None;
+
+        self.drained.wait_until(|| {
+            if epoch.is_none() {
+                epoch = self.try_flip();
+            }
+
+            match epoch {
+                Some(epoch) => self.is_drained(epoch),
+                None        => false
+            }
+        })
+    }
+}
+
+//the point in time a wait_for_readers call was made...done once every reader from
+//before that point has dropped
+pub struct ReaderFence<'locked, T : 'locked> {
+    owner : &'locked RWVec<T>,
+    //None until we've managed to flip the epoch
+    epoch : Option<usize>
+}
+
+impl<'locked, T> ReaderFence<'locked, T> {
+    pub fn is_done(&mut self) -> bool {
+        let epochs = &self.owner.epochs;
+
+        if self.epoch.is_none() {
+            self.epoch = epochs.try_flip();
+        }
+
+        match self.epoch {
+            Some(epoch) => epochs.is_drained(epoch),
+            None        => false
+        }
+    }
+
+    pub fn wait(mut self) {
+        let owner = self.owner;
+        owner.epochs.drained.wait_until(|| self.is_done())
+    }
+
+    pub fn wait_timeout(mut self, timeout : Duration) -> bool {
+        let owner = self.owner;
+        owner.epochs.drained.wait_until_timeout(timeout, || self.is_done())
+    }
+}
+
+impl<'locked, T> Future for ReaderFence<'locked, T> {
+    type Output = ();
+
+    fn poll(self : Pin<&mut Self>, cx : &mut Context) -> Poll<()> {
+        let fence = self.get_mut();
+        let owner = fence.owner;
+        owner.epochs.drained.poll_until(cx, || fence.is_done())
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 SNAPSHOTS                                 //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//a frozen copy of the vec tagged with the version it was taken at...cloning it is
+//just an Arc bump so it can be handed around freely
+pub struct Snapshot<T> {
+    data    : Arc<std::vec::Vec<T>>,
+    version : usize
+}
+
+impl<T> Snapshot<T> {
+    pub fn version(&self) -> usize {
+        self.version
+    }
+}
+
+impl<T : PartialEq> Snapshot<T> {
+    //what changed between this snapshot and newer, which can be a later snapshot or a
+    //guard on the live vec...enough to patch a copy of this one into the newer state
+    pub fn diff(&self, newer : &[T]) -> Diff {
+        let older  = &self.data[..];
+        let common = std::cmp::min(older.len(), newer.len());
+
+        Diff {
+            modified  : (0..common).filter(|&i| older[i] != newer[i]).collect(),
+            appended  : common..newer.len(),
+            truncated : if newer.len() < older.len() { Some(newer.len()) } else { None }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diff {
+    //indices present in both whose elements differ
+    pub modified  : std::vec::Vec<usize>,
+    //indices that are new...empty unless the vec grew
+    pub appended  : std::ops::Range<usize>,
+    //the len the vec was cut down to, if it shrank
+    pub truncated : Option<usize>
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.appended.start == self.appended.end && self.truncated.is_none()
+    }
+}
+
+impl<T> Clone for Snapshot<T> {
+    fn clone(&self) -> Snapshot<T> {
+        Snapshot {
+            data    : self.data.clone(),
+            version : self.version
+        }
+    }
+}
+
+impl<T> Deref for Snapshot<T> {
+    type Target = [T];
+
+    fn deref<'a>(&'a self) -> &'a [T] {
+        &self.data[..]
+    }
+}
+
+impl<T : Clone> RWVec<T> {
+    //copies the current contents out under a short read lock
+    pub fn snapshot(&self) -> Snapshot<T> {
+        //read the version first so the copy is at least this new...if something
+        //lands in between the next check just sees a newer version and copies again
+        let version = self.version();
+        let reader  = self.reader();
+
+        Snapshot {
+            data    : Arc::new(reader.to_vec()),
+            version : version
+        }
+    }
+
+    //copies the contents out size elements at a time, taking the read lock only for
+    //each copy...whatever the caller does with a chunk happens with no lock held. the
+    //chunks don't add up to one consistent snapshot if the vec changes in between,
+    //pushes made while iterating show up at the end
+    pub fn iter_cloned_chunks(&self, size : usize) -> ClonedChunks<'_, T> {
+        assert!(size > 0, "chunk size must be at least 1");
+
+        ClonedChunks {
+            owner    : self,
+            position : 0,
+            size     : size
+        }
+    }
+}
+
+pub struct ClonedChunks<'locked, T : 'locked> {
+    owner    : &'locked RWVec<T>,
+    position : usize,
+    size     : usize
+}
+
+impl<'locked, T : Clone> Iterator for ClonedChunks<'locked, T> {
+    type Item = std::vec::Vec<T>;
+
+    fn next(&mut self) -> Option<std::vec::Vec<T>> {
+        let reader = self.owner.reader();
+        if self.position >= reader.len() {
+            return None
+        }
+
+        let end   = std::cmp::min(self.position + self.size, reader.len());
+        let chunk = reader[self.position..end].to_vec();
+        self.position = end;
+
+        Some(chunk)
+    }
+}
+
+//a per-thread cache of the latest snapshot...get() only touches the locks when the
+//version has moved since the last copy, so read heavy threads mostly just do an
+//atomic load. keep one of these per thread (in a thread_local or on the stack of a
+//long running loop), it is deliberately not Sync
+pub struct CachedReader<T> {
+    owner  : Arc<RWVec<T>>,
+    cached : Option<Snapshot<T>>,
+    marker : PhantomData<*const ()>
+}
+
+impl<T : Clone> CachedReader<T> {
+    pub fn new(owner : Arc<RWVec<T>>) -> CachedReader<T> {
+        CachedReader {
+            owner  : owner,
+            cached : None,
+            marker : PhantomData
+        }
+    }
+
+    pub fn get(&mut self) -> &Snapshot<T> {
+        let stale = match self.cached {
+            Some(ref snapshot) => snapshot.version != self.owner.version(),
+            None               => true
+        };
+
+        if stale {
+            self.cached = Some(self.owner.snapshot());
+        }
+
+        self.cached.as_ref().unwrap()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 SWAP VEC                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//read-optimized sibling of RWVec for read-mostly tables...the current contents are
+//published as an Arc behind an atomic pointer, so reader() never touches a lock: it
+//registers in an epoch slot, loads the pointer, bumps the refcount and leaves, all
+//with plain atomics. every change builds a whole new Vec and swaps it in, so pushes
+//cost a copy of the contents...only worth it when writes are rare
+pub struct RWSwapVec<T> {
+    current : AtomicPtr<std::vec::Vec<T>>,
+    //readers in the middle of picking up the pointer
+    epochs  : ReaderEpochs,
+    //writers take turns building the next version
+    write   : Mutex<()>,
+    marker  : PhantomData<Arc<std::vec::Vec<T>>>
+}
+
+impl<T> RWSwapVec<T> {
+    pub fn new() -> Arc<RWSwapVec<T>> {
+        RWSwapVec::from_vec(std::vec::Vec::new())
+    }
+
+    pub fn from_vec(vec : std::vec::Vec<T>) -> Arc<RWSwapVec<T>> {
+        Arc::new(RWSwapVec {
+            current : AtomicPtr::new(Arc::into_raw(Arc::new(vec)) as *mut std::vec::Vec<T>),
+            epochs  : ReaderEpochs::new(),
+            write   : Mutex::new(()),
+            marker  : PhantomData
+        })
+    }
+
+    //the current contents...the Arc stays valid no matter what gets published after
+    pub fn reader(&self) -> Arc<std::vec::Vec<T>> {
+        let slot = self.epochs.enter();
+
+        let current = unsafe {
+            let raw = self.current.load(Ordering::SeqCst);
+            Arc::increment_strong_count(raw);
+            Arc::from_raw(raw)
+        };
+
+        self.epochs.leave(slot);
+        current
+    }
+
+    //replaces the contents wholesale
+    pub fn store(&self, vec : std::vec::Vec<T>) {
+        let _write = self.write.lock().unwrap();
+        self.publish(vec);
+    }
+
+    //has to be called with the write mutex held
+    fn publish(&self, vec : std::vec::Vec<T>) {
+        let next = Arc::into_raw(Arc::new(vec)) as *mut std::vec::Vec<T>;
+        let old  = self.current.swap(next, Ordering::SeqCst);
+
+        //anyone who could still be about to bump the old refcount entered before the
+        //swap, so once they are gone our reference can go
+        self.epochs.quiesce();
+        unsafe { drop(Arc::from_raw(old)); }
+    }
+}
+
+impl<T : Clone> RWSwapVec<T> {
+    //builds the next version from a copy of the current one
+    pub fn update<F>(&self, f : F) where F : FnOnce(&mut std::vec::Vec<T>) {
+        let _write = self.write.lock().unwrap();
+
+        let mut next = self.reader().to_vec();
+        f(&mut next);
+        self.publish(next);
+    }
+
+    pub fn push(&self, t : T) {
+        self.update(move |vec| vec.push(t))
+    }
+}
+
+impl<T> Drop for RWSwapVec<T> {
+    fn drop(&mut self) {
+        unsafe { drop(Arc::from_raw(*self.current.get_mut())); }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 WATCHDOG                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//what the watchdog hands its callback about a guard that has overstayed its welcome
+#[cfg(feature = "watchdog")]
+pub struct HeldGuard {
+    //the name of the vec, if it was given one
+    pub vec      : Option<String>,
+    //read, write or exclusive
+    pub kind     : &'static str,
+    //where reader(), writer() or upgrade() was called
+    pub location : &'static Location<'static>,
+    //the thread that created the guard, if it had a name
+    pub thread   : Option<String>,
+    //how long it had been held when it was reported
+    pub held     : Duration
+}
+
+#[cfg(feature = "watchdog")]
+struct LiveGuard {
+    kind     : &'static str,
+    location : &'static Location<'static>,
+    thread   : Option<String>,
+    since    : Instant,
+    reported : bool
+}
+
+//every live guard registers here...a background thread walks the list and reports
+//the ones that are over the limit. the thread only holds a weak reference so it
+//goes away with the vec
+#[cfg(feature = "watchdog")]
+struct Watchdog {
+    name     : Option<String>,
+    limit    : Duration,
+    callback : Arc<dyn Fn(&HeldGuard) + Send + Sync>,
+    guards   : Mutex<HashMap<usize, LiveGuard>>,
+    next_id  : AtomicUsize
+}
+
+#[cfg(feature = "watchdog")]
+impl Watchdog {
+    fn spawn(name : Option<String>, limit : Duration, callback : Arc<dyn Fn(&HeldGuard) + Send + Sync>) -> Arc<Watchdog> {
+        let watchdog = Arc::new(Watchdog {
+            name     : name,
+            limit    : limit,
+            callback : callback,
+            guards   : Mutex::new(HashMap::new()),
+            next_id  : AtomicUsize::new(0)
+        });
+
+        let weak   = Arc::downgrade(&watchdog);
+        let period = std::cmp::max(limit / 4, Duration::from_millis(1));
+        let thread = match watchdog.name {
+            Some(ref name) => format!("rwvec-watchdog-{}", name),
+            None           => "rwvec-watchdog".to_string()
+        };
+
+        std::thread::Builder::new().name(thread).spawn(move || {
+            loop {
+                std::thread::sleep(period);
+
+                match weak.upgrade() {
+                    Some(watchdog) => watchdog.check(),
+                    None           => return
+                }
+            }
+        }).unwrap();
+
+        watchdog
+    }
+
+    fn check(&self) {
+        let mut overdue = std::vec::Vec::new();
+
+        {
+            let mut guards = self.guards.lock().unwrap();
+            for guard in guards.values_mut() {
+                let held = guard.since.elapsed();
+                if !guard.reported && held >= self.limit {
+                    guard.reported = true;
+                    overdue.push(HeldGuard {
+                        vec      : self.name.clone(),
+                        kind     : guard.kind,
+                        location : guard.location,
+                        thread   : guard.thread.clone(),
+                        held     : held
+                    });
+                }
+            }
+        }
+
+        //call out without the registry locked so the callback can do whatever it wants
+        for guard in &overdue {
+            (self.callback)(guard);
+        }
+    }
+
+    #[track_caller]
+    fn register(&self, kind : &'static str) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        self.guards.lock().unwrap().insert(id, LiveGuard {
+            kind     : kind,
+            location : Location::caller(),
+            thread   : std::thread::current().name().map(|name| name.to_string()),
+            since    : Instant::now(),
+            reported : false
+        });
+
+        id
+    }
+
+    fn unregister(&self, id : usize) {
+        self.guards.lock().unwrap().remove(&id);
+    }
+}
+
+//every guard carries one of these...with the watchdog and lock debugging compiled out
+//it is empty
+struct GuardTicket<'locked> {
+    #[cfg(feature = "watchdog")]
+    watchdog : Option<(&'locked Watchdog, usize)>,
+    #[cfg(feature = "debug-locks")]
+    lock_log : Option<(&'locked LockLog, usize)>,
+    //which named vec, what kind of guard and since when, for the hold time histogram
+    #[cfg(feature = "metrics")]
+    held     : Option<(&'locked str, &'static str, Instant)>,
+    #[cfg(feature = "log")]
+    slow     : Option<(&'locked SlowLocks, &'static str, Instant)>,
+    marker   : PhantomData<&'locked ()>
+}
+
+impl<'locked> GuardTicket<'locked> {
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    #[allow(unused_variables)]
+    fn new<T>(owner : &'locked RWVec<T>, kind : &'static str) -> GuardTicket<'locked> {
+        GuardTicket {
+            #[cfg(feature = "watchdog")]
+            //no closure here, track_caller doesn't see through them
+            watchdog : match owner.watchdog {
+                Some(ref watchdog) => Some((&**watchdog, watchdog.register(kind))),
+                None               => None
+            },
+            #[cfg(feature = "debug-locks")]
+            lock_log : match owner.lock_log.record(kind) {
+                Some(id) => Some((&owner.lock_log, id)),
+                None     => None
+            },
+            #[cfg(feature = "metrics")]
+            held     : owner.name.as_ref().map(|name| (&name[..], kind, Instant::now())),
+            #[cfg(feature = "log")]
+            slow     : owner.slow_locks.as_ref().map(|slow_locks| (slow_locks, kind, Instant::now())),
+            marker   : PhantomData
+        }
+    }
+}
+
+impl<'locked> Drop for GuardTicket<'locked> {
+    fn drop(&mut self) {
+        #[cfg(feature = "watchdog")]
+        {
+            if let Some((watchdog, id)) = self.watchdog {
+                watchdog.unregister(id);
+            }
+        }
+
+        #[cfg(feature = "debug-locks")]
+        {
+            if let Some((lock_log, id)) = self.lock_log {
+                lock_log.release(id);
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            if let Some((name, kind, since)) = self.held {
+                histogram!("rwvec_guard_hold_seconds", "vec" => name.to_string(), "kind" => kind).record(since.elapsed().as_secs_f64());
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            if let Some((slow_locks, kind, since)) = self.slow {
+                slow_locks.held(kind, since);
+            }
+        }
+    }
+}
+
+//the thresholds from slow_lock_warnings, plus the label to put in the warnings
+#[cfg(feature = "log")]
+struct SlowLocks {
+    label   : String,
+    acquire : Duration,
+    hold    : Duration
+}
+
+#[cfg(feature = "log")]
+impl SlowLocks {
+    fn waited(&self, kind : &'static str, asked : Instant) {
+        let waited = asked.elapsed();
+        if waited > self.acquire {
+            warn!("{}: waited {:?} for the {} lock (threshold {:?})", self.label, waited, kind, self.acquire);
+        }
+    }
+
+    fn held(&self, kind : &'static str, since : Instant) {
+        let held = since.elapsed();
+        if held > self.hold {
+            warn!("{}: {} guard held for {:?} (threshold {:?})", self.label, kind, held, self.hold);
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                   QUEUE                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+struct Queue<T> {
+    vec     : Arc<RWVec<T>>,
+    //once this hits zero and the backlog is empty receivers stop waiting
+    senders : AtomicUsize
+}
+
+impl<T> Queue<T> {
+    fn try_take(&self) -> Option<T> {
+        if self.vec.visible_len() == 0 {
+            return None
+        }
+
+        let mut vec = self.vec.exclusive();
+        if vec.is_empty() {
+            None
+        } else {
+            Some(vec.remove(0))
+        }
+    }
+
+    fn disconnected(&self) -> bool {
+        self.senders.load(Ordering::SeqCst) == 0
+    }
+}
+
+pub struct Sender<T> {
+    queue : Arc<Queue<T>>
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, t : T) {
+        self.queue.vec.push(t)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.queue.senders.fetch_add(1, Ordering::SeqCst);
+        Sender { queue : self.queue.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.queue.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            //wake up the receivers so they can see there's nothing more coming
+            self.queue.vec.changed.notify();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    queue : Arc<Queue<T>>
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&self) -> Option<T> {
+        self.queue.try_take()
+    }
+
+    //blocks until an element comes in...None once every Sender is gone and the
+    //backlog has been drained
+    pub fn recv(&self) -> Option<T> {
+        let queue = &self.queue;
+
+        loop {
+            let seen = queue.vec.pushes.load(Ordering::SeqCst);
+
+            if let Some(t) = queue.try_take() {
+                return Some(t)
+            }
+
+            if queue.disconnected() {
+                //one last look in case the final sends landed after we checked
+                return queue.try_take()
+            }
+
+            queue.vec.changed.wait_until(|| queue.vec.has_new_elements(seen) || queue.disconnected());
+        }
+    }
+
+    //recv that gives up with None once timeout has passed
+    pub fn recv_timeout(&self, timeout : Duration) -> Option<T> {
+        let queue    = &self.queue;
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let seen = queue.vec.pushes.load(Ordering::SeqCst);
+
+            if let Some(t) = queue.try_take() {
+                return Some(t)
+            }
+
+            if queue.disconnected() {
+                return queue.try_take()
+            }
+
+            let now = Instant::now();
+            if now >= deadline || !queue.vec.changed.wait_until_timeout(deadline - now, || queue.vec.has_new_elements(seen) || queue.disconnected()) {
+                return None
+            }
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        Receiver { queue : self.queue.clone() }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                              ATOMIC ELEMENTS                              //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//element types that can be changed through a shared reference...for these a single
+//element never needs the write lock, only the buffer moving does
+pub trait AtomicElement {
+    type Value : Copy;
+
+    fn load(&self) -> Self::Value;
+    fn store(&self, value : Self::Value);
+    fn fetch_add(&self, value : Self::Value) -> Self::Value;
+}
+
+macro_rules! atomic_element {
+    ($($atomic:ident : $value:ty),*) => {
+        $(
+            impl AtomicElement for std::sync::atomic::$atomic {
+                type Value = $value;
+
+                fn load(&self) -> $value {
+                    std::sync::atomic::$atomic::load(self, Ordering::SeqCst)
+                }
+
+                fn store(&self, value : $value) {
+                    std::sync::atomic::$atomic::store(self, value, Ordering::SeqCst)
+                }
+
+                fn fetch_add(&self, value : $value) -> $value {
+                    std::sync::atomic::$atomic::fetch_add(self, value, Ordering::SeqCst)
+                }
+            }
+        )*
+    }
+}
+
+atomic_element!(AtomicUsize : usize, AtomicIsize : isize,
+                AtomicU64   : u64,   AtomicI64   : i64,
+                AtomicU32   : u32,   AtomicI32   : i32,
+                AtomicU16   : u16,   AtomicI16   : i16,
+                AtomicU8    : u8,    AtomicI8    : i8);
+
+impl<T : AtomicElement> RWVec<T> {
+    pub fn load_at(&self, index : usize) -> T::Value {
+        self.pinned(index, |t| t.load())
+    }
+
+    pub fn store_at(&self, index : usize, value : T::Value) {
+        self.pinned(index, |t| t.store(value))
+    }
+
+    //hands back the previous value, wrapping around on overflow
+    pub fn fetch_add_at(&self, index : usize, value : T::Value) -> T::Value {
+        self.pinned(index, |t| t.fetch_add(value))
+    }
+
+    //the element itself is changed atomically, so all that's needed is to keep the
+    //buffer from moving while we touch it...that is one bump of this thread's own reader
+    //counter, no guard, epoch or watchdog bookkeeping. only pushes that realloc (and
+    //anything else structural) have to wait for it
+    //
+    //this can't go fully lock-free: a realloc (or a preallocate swap) frees the old
+    //buffer, and an update that landed there after the copy would be lost or would touch
+    //freed memory. nothing short of the read counter tells the writer someone is still in
+    //there, and preallocate copies under the write lock for exactly that reason
+    fn pinned<F, R>(&self, index : usize, f : F) -> R where F : FnOnce(&T) -> R {
+        unsafe {
+            let slot = self.rw_lock.read();
+            let len  = self.visible_len();
+
+            if index >= len {
+                self.rw_lock.read_unlock(&slot);
+                panic!("{}: index {} out of bounds (len {})", self.label(), index, len);
+            }
+
+            let result = f(&*self.vec().as_ptr().offset(index as isize));
+            self.rw_lock.read_unlock(&slot);
+            result
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  BIT VEC                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+const WORD_BITS : usize = 8 * mem::size_of::<usize>();
+
+//a bitset on top of an RWVec of words...bits are set, cleared and tested atomically
+//without any lock, appends go through the vec's push path
+pub struct RWBitVec {
+    words  : Arc<RWVec<AtomicUsize>>,
+    //bits appended so far...bits past this in the last word are always clear
+    len    : AtomicUsize,
+    //appends claim a bit and maybe a new word, one at a time
+    append : Mutex<()>
+}
+
+impl RWBitVec {
+    pub fn new() -> Arc<RWBitVec> {
+        RWBitVec::with_capacity(0)
+    }
+
+    pub fn with_capacity(bits : usize) -> Arc<RWBitVec> {
+        Arc::new(RWBitVec {
+            words  : RWVec::with_capacity(bits.div_ceil(WORD_BITS)),
+            len    : AtomicUsize::new(0),
+            append : Mutex::new(())
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    //appends one bit and returns its index
+    pub fn push(&self, bit : bool) -> usize {
+        let _append = self.append.lock().unwrap();
+        let index   = self.len.load(Ordering::SeqCst);
+
+        if index.is_multiple_of(WORD_BITS) {
+            self.words.push(AtomicUsize::new(0));
+        }
+
+        if bit {
+            self.words.pinned(index / WORD_BITS, |word| word.fetch_or(mask(index), Ordering::SeqCst));
+        }
+
+        self.len.store(index + 1, Ordering::SeqCst);
+        index
+    }
+
+    //appends count clear bits and returns the index of the first one
+    pub fn grow(&self, count : usize) -> usize {
+        let _append = self.append.lock().unwrap();
+        let first   = self.len.load(Ordering::SeqCst);
+        let words   = (first + count).div_ceil(WORD_BITS);
+
+        for _ in first.div_ceil(WORD_BITS)..words {
+            self.words.push(AtomicUsize::new(0));
+        }
+
+        self.len.store(first + count, Ordering::SeqCst);
+        first
+    }
+
+    pub fn test(&self, index : usize) -> bool {
+        self.check(index);
+        self.words.load_at(index / WORD_BITS) & mask(index) != 0
+    }
+
+    //returns whether the bit was already set
+    pub fn set(&self, index : usize) -> bool {
+        self.check(index);
+        self.words.pinned(index / WORD_BITS, |word| word.fetch_or(mask(index), Ordering::SeqCst)) & mask(index) != 0
+    }
+
+    //returns whether the bit was set
+    pub fn clear(&self, index : usize) -> bool {
+        self.check(index);
+        self.words.pinned(index / WORD_BITS, |word| word.fetch_and(!mask(index), Ordering::SeqCst)) & mask(index) != 0
+    }
+
+    //the indices of every set bit, all read under one guard...bits flipped while we
+    //walk the words may or may not show up, but each word is read atomically
+    pub fn ones(&self) -> std::vec::Vec<usize> {
+        let len   = self.len();
+        let words = self.words.reader();
+        let mut ones = std::vec::Vec::new();
+
+        for (i, word) in words.iter().enumerate() {
+            let mut bits = word.load(Ordering::SeqCst);
+
+            while bits != 0 {
+                let index = i * WORD_BITS + bits.trailing_zeros() as usize;
+                if index >= len {
+                    break
+                }
+
+                ones.push(index);
+                bits &= bits - 1;
+            }
+        }
+
+        ones
+    }
+
+    fn check(&self, index : usize) {
+        let len = self.len();
+        if index >= len {
+            panic!("RWBitVec: index {} out of bounds (len {})", index, len);
+        }
+    }
+}
+
+fn mask(index : usize) -> usize {
+    1 << (index % WORD_BITS)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                   BYTES                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+impl RWVec<u8> {
+    //a handle encoders and serializers can stream into...every write lands as one batch
+    //under the push lock, so a write is never interleaved with other pushers
+    pub fn byte_writer(&self) -> ByteWriter<'_> {
+        ByteWriter { owner : self }
+    }
+
+    //reads the bytes from the start...see ByteCursor::blocking for using it as a pipe
+    pub fn byte_cursor(&self) -> ByteCursor<'_> {
+        self.byte_cursor_at(0)
+    }
+
+    //picks up where a cursor left off, given what its position() was...positions are
+    //plain byte offsets so they can be saved anywhere and outlive the cursor
+    pub fn byte_cursor_at(&self, position : u64) -> ByteCursor<'_> {
+        ByteCursor {
+            owner    : self,
+            position : position,
+            blocking : false,
+            buffer   : std::vec::Vec::new(),
+            start    : 0
+        }
+    }
+}
+
+pub struct ByteWriter<'vec> {
+    owner : &'vec RWVec<u8>
+}
+
+impl<'vec> io::Write for ByteWriter<'vec> {
+    fn write(&mut self, buf : &[u8]) -> io::Result<usize> {
+        self.owner.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf : &[u8]) -> io::Result<()> {
+        self.owner.extend_from_slice(buf);
+        Ok(())
+    }
+
+    //everything written is already visible to readers
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//a position in the bytes...each read copies out under a short lived reader so pushers
+//are never held up for long
+pub struct ByteCursor<'vec> {
+    owner    : &'vec RWVec<u8>,
+    //where the next byte handed out comes from
+    position : u64,
+    //wait for more bytes at the end instead of reporting end of file
+    blocking : bool,
+    //bytes copied out for BufRead...buffer[start..] are the ones at position onwards
+    buffer   : std::vec::Vec<u8>,
+    start    : usize
+}
+
+//how much BufRead copies out per refill
+const BYTE_CURSOR_BUFFER : usize = 8 * 1024;
+
+impl<'vec> ByteCursor<'vec> {
+    //reads at the end sleep until something gets pushed, which turns writer and cursor
+    //into a pipe that can be replayed from any position
+    pub fn blocking(mut self) -> ByteCursor<'vec> {
+        self.blocking = true;
+        self
+    }
+
+    //the offset of the next byte this will hand out, for byte_cursor_at to resume from
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    //copies whatever is there from at onwards, waiting for something first if blocking
+    fn copy_out(&self, at : u64, buf : &mut [u8]) -> usize {
+        let owner = self.owner;
+        if self.blocking {
+            owner.changed.wait_until(|| owner.visible_len() as u64 > at);
+        }
+
+        let bytes = owner.reader();
+        if bytes.len() as u64 <= at {
+            return 0
+        }
+
+        let start = at as usize;
+        let count = std::cmp::min(buf.len(), bytes.len() - start);
+        buf[..count].copy_from_slice(&bytes[start..start + count]);
+        count
+    }
+
+    fn discard_buffer(&mut self) {
+        self.buffer.clear();
+        self.start = 0;
+    }
+}
+
+impl<'vec> io::Read for ByteCursor<'vec> {
+    fn read(&mut self, buf : &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0)
+        }
+
+        //big reads with nothing buffered skip the extra copy
+        if self.start == self.buffer.len() && buf.len() >= BYTE_CURSOR_BUFFER {
+            let count = self.copy_out(self.position, buf);
+            self.position += count as u64;
+            return Ok(count)
+        }
+
+        let count = {
+            let buffered = io::BufRead::fill_buf(self)?;
+            let count    = std::cmp::min(buf.len(), buffered.len());
+            buf[..count].copy_from_slice(&buffered[..count]);
+            count
+        };
+
+        io::BufRead::consume(self, count);
+        Ok(count)
+    }
+}
+
+impl<'vec> io::BufRead for ByteCursor<'vec> {
+    //refills from the vec whenever the buffer has been used up, so read_line and lines
+    //keep picking up bytes pushed after the cursor was made
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.start == self.buffer.len() {
+            let mut buffer = mem::take(&mut self.buffer);
+            buffer.resize(BYTE_CURSOR_BUFFER, 0);
+
+            let count = self.copy_out(self.position, &mut buffer);
+            buffer.truncate(count);
+
+            self.buffer = buffer;
+            self.start  = 0;
+        }
+
+        Ok(&self.buffer[self.start..])
+    }
+
+    fn consume(&mut self, count : usize) {
+        let count = std::cmp::min(count, self.buffer.len() - self.start);
+        self.start    += count;
+        self.position += count as u64;
+    }
+}
+
+impl<'vec> io::Seek for ByteCursor<'vec> {
+    //seeking past the end is fine, reads there just find nothing (or wait)
+    fn seek(&mut self, from : io::SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match from {
+            io::SeekFrom::Start(position) => {
+                self.discard_buffer();
+                self.position = position;
+                return Ok(position)
+            },
+            io::SeekFrom::End(offset)     => (self.owner.visible_len() as u64, offset),
+            io::SeekFrom::Current(offset) => (self.position, offset)
+        };
+
+        let position = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+
+        match position {
+            Some(position) => {
+                self.discard_buffer();
+                self.position = position;
+                Ok(position)
+            },
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                   ASYNC                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//everything in here is plain std::future and std::task...wakers are woken from whatever
+//thread changed the vec, so it works the same under any executor and none of them has
+//to be running for pushes and readers to make progress
+
+#[cfg(feature = "async")]
+impl<T> RWVec<T> {
+    //ready with a permit once the vec has room for one more element, which stays set
+    //aside for it until the permit pushes or is dropped...a bounded vec makes async
+    //producers wait here instead of blocking the executor thread in push
+    pub fn poll_reserve(&self, cx : &mut Context) -> Poll<Permit<'_, T>> {
+        let limit = match self.bound {
+            Some((limit, _)) => limit,
+            None             => return Poll::Ready(Permit { owner : self, reserved : false })
+        };
+
+        loop {
+            //a pusher or an exclusive section has the lock...waiting for it would block
+            //the executor thread, so come back on the next poll instead
+            if !self.push_lock.try_lock() {
+                cx.waker().wake_by_ref();
+                return Poll::Pending
+            }
+
+            //claimed under the push lock so no push can take the slot in between
+            let room = self.occupied() < limit;
+            if room {
+                self.reserved.fetch_add(1, Ordering::SeqCst);
+            }
+            unsafe { self.push_lock.unlock(); }
+
+            if room {
+                return Poll::Ready(Permit { owner : self, reserved : true })
+            }
+
+            //only looks at the count while registering, the claim happens out here
+            if self.changed.poll_until(cx, || self.occupied() < limit).is_pending() {
+                return Poll::Pending
+            }
+        }
+    }
+
+    //poll_reserve as a future
+    pub fn reserve_slot(&self) -> ReserveSlot<'_, T> {
+        ReserveSlot { owner : self }
+    }
+}
+
+#[cfg(feature = "async")]
+pub struct ReserveSlot<'locked, T : 'locked> {
+    owner : &'locked RWVec<T>
+}
+
+#[cfg(feature = "async")]
+impl<'locked, T> Future for ReserveSlot<'locked, T> {
+    type Output = Permit<'locked, T>;
+
+    fn poll(self : Pin<&mut Self>, cx : &mut Context) -> Poll<Permit<'locked, T>> {
+        self.owner.poll_reserve(cx)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Receiver<T> {
+    //recv for async consumers...ready with None once every Sender is gone and the
+    //backlog has been drained
+    pub fn poll_recv(&self, cx : &mut Context) -> Poll<Option<T>> {
+        let queue = &self.queue;
+
+        loop {
+            let seen = queue.vec.pushes.load(Ordering::SeqCst);
+
+            if let Some(t) = queue.try_take() {
+                return Poll::Ready(Some(t))
+            }
+
+            if queue.disconnected() {
+                //one last look in case the final sends landed after we checked
+                return Poll::Ready(queue.try_take())
+            }
+
+            //only looks while registering...taking in here would announce the removal
+            //back into the wakers lock poll_until is holding
+            let ready = queue.vec.changed.poll_until(cx, || queue.vec.has_new_elements(seen) || queue.disconnected());
+            if ready.is_pending() {
+                return Poll::Pending
+            }
+        }
+    }
+}
+
+//lets a Receiver be used with the combinators of any futures based runtime
+#[cfg(all(feature = "async", feature = "futures-core"))]
+impl<T> futures_core::Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self : Pin<&mut Self>, cx : &mut Context) -> Poll<Option<T>> {
+        self.poll_recv(cx)
+    }
+}
+
+//a slot in a bounded vec that nobody else can push into
+#[cfg(feature = "async")]
+pub struct Permit<'locked, T : 'locked> {
+    owner    : &'locked RWVec<T>,
+    //unbounded vecs hand out permits without setting anything aside
+    reserved : bool
+}
+
+#[cfg(feature = "async")]
+impl<'locked, T> Permit<'locked, T> {
+    //never blocks and never finds the vec full
+    pub fn push(self, t : T) -> usize {
+        let owner = self.owner;
+        let admission = if self.reserved { Admission::Reserved } else { Admission::Policy };
+        let pushed    = owner.push_then(move |_| t, |_, index| index, admission);
+        //the slot is used up now
+        mem::forget(self);
+
+        match pushed {
+            Ok((index, preallocate)) => {
+                if preallocate {
+                    owner.preallocate();
+                }
+
+                index
+            },
+            Err((error, _)) => owner.push_failed(error)
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'locked, T> Drop for Permit<'locked, T> {
+    //an unused permit gives its slot back to whoever is waiting for room
+    fn drop(&mut self) {
+        if self.reserved {
+            self.owner.reserved.fetch_sub(1, Ordering::SeqCst);
+            self.owner.changed.notify();
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                              LOCK DEBUGGING                               //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//how many of the latest read guards get remembered
+#[cfg(feature = "debug-locks")]
+const RECENT_READERS : usize = 16;
+
+//where a guard was made and by whom
+#[cfg(feature = "debug-locks")]
+#[derive(Clone, Debug)]
+pub struct LockHolder {
+    //read, write or exclusive
+    pub kind     : &'static str,
+    //where reader(), writer() or upgrade() was called
+    pub location : &'static Location<'static>,
+    pub thread   : Option<String>,
+    pub since    : Instant
+}
+
+//what dump() hands back...enough to see who a hung thread is waiting on
+#[cfg(feature = "debug-locks")]
+#[derive(Clone, Debug)]
+pub struct LockDump {
+    //the write or exclusive guard that currently holds the write lock
+    pub writer         : Option<LockHolder>,
+    //the latest read guards, oldest first...they may have been dropped since
+    pub recent_readers : std::vec::Vec<LockHolder>
+}
+
+#[cfg(feature = "debug-locks")]
+struct LockLog {
+    writer  : Mutex<Option<(usize, LockHolder)>>,
+    readers : Mutex<VecDeque<LockHolder>>,
+    next_id : AtomicUsize
+}
+
+#[cfg(feature = "debug-locks")]
+impl LockLog {
+    fn new() -> LockLog {
+        LockLog {
+            writer  : Mutex::new(None),
+            readers : Mutex::new(VecDeque::with_capacity(RECENT_READERS)),
+            next_id : AtomicUsize::new(0)
+        }
+    }
+
+    //returns an id for write guards, which have to be released again
+    #[track_caller]
+    fn record(&self, kind : &'static str) -> Option<usize> {
+        let holder = LockHolder {
+            kind     : kind,
+            location : Location::caller(),
+            thread   : std::thread::current().name().map(|name| name.to_string()),
+            since    : Instant::now()
+        };
+
+        if kind == "read" {
+            let mut readers = self.readers.lock().unwrap();
+            if readers.len() == RECENT_READERS {
+                readers.pop_front();
+            }
+            readers.push_back(holder);
+            return None
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        *self.writer.lock().unwrap() = Some((id, holder));
+        Some(id)
+    }
+
+    fn release(&self, id : usize) {
+        let mut writer = self.writer.lock().unwrap();
+
+        //an upgraded guard's new ticket may already have taken over
+        let ours = match *writer {
+            Some((holder, _)) => holder == id,
+            None              => false
+        };
+
+        if ours {
+            *writer = None;
+        }
+    }
+}
+
+#[cfg(feature = "debug-locks")]
+impl<T> RWVec<T> {
+    pub fn dump(&self) -> LockDump {
+        LockDump {
+            writer         : self.lock_log.writer.lock().unwrap().as_ref().map(|(_, holder)| holder.clone()),
+            recent_readers : self.lock_log.readers.lock().unwrap().iter().cloned().collect()
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 EVENT LOG                                 //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//how many structural changes the log remembers
+#[cfg(feature = "debug-events")]
+const EVENT_LOG_LEN : usize = 256;
+
+#[cfg(feature = "debug-events")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    //elements landed at start..end
+    Pushed    { start : usize, end : usize },
+    //the buffer moved, capacity went from from to to
+    Realloc   { from : usize, to : usize },
+    //a full bounded vec threw out its oldest elements
+    Evicted   { count : usize },
+    //exclusive access was dropped...pops, drains, truncates etc show up as after < before
+    Exclusive { before : usize, after : usize },
+    //a writer was upgraded to exclusive access
+    Upgrade
+}
+
+#[cfg(feature = "debug-events")]
+#[derive(Clone, Debug)]
+pub struct LoggedEvent {
+    pub event  : Event,
+    pub at     : Instant,
+    pub thread : std::thread::ThreadId
+}
+
+#[cfg(feature = "debug-events")]
+struct EventLog {
+    events : Mutex<VecDeque<LoggedEvent>>
+}
+
+#[cfg(feature = "debug-events")]
+impl EventLog {
+    fn new() -> EventLog {
+        EventLog { events : Mutex::new(VecDeque::with_capacity(EVENT_LOG_LEN)) }
+    }
+
+    fn record(&self, event : Event) {
+        let logged = LoggedEvent {
+            event  : event,
+            at     : Instant::now(),
+            thread : std::thread::current().id()
+        };
+
+        let mut events = self.events.lock().unwrap();
+        if events.len() == EVENT_LOG_LEN {
+            events.pop_front();
+        }
+        events.push_back(logged);
+    }
+}
+
+#[cfg(feature = "debug-events")]
+impl<T> RWVec<T> {
+    //the latest structural changes, oldest first...for working out after the fact how
+    //the vec got into the state it's in
+    pub fn events(&self) -> std::vec::Vec<LoggedEvent> {
+        self.event_log.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  HANDLES                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//the push half of split()
+pub struct Producer<T> {
+    vec : Arc<RWVec<T>>
+}
+
+impl<T> Producer<T> {
+    pub fn push(&self, t : T) {
+        self.vec.push(t)
+    }
+
+    pub fn push_indexed(&self, t : T) -> usize {
+        self.vec.push_indexed(t)
+    }
+
+    pub fn push_with<F>(&self, f : F) -> usize where F : FnOnce(usize) -> T {
+        self.vec.push_with(f)
+    }
+
+    pub fn try_push(&self, t : T) -> Result<usize, T> {
+        self.vec.try_push(t)
+    }
+}
+
+impl<T> Clone for Producer<T> {
+    fn clone(&self) -> Producer<T> {
+        Producer { vec : self.vec.clone() }
+    }
+}
+
+//the read half of split()
+pub struct ReaderFactory<T> {
+    vec : Arc<RWVec<T>>
+}
+
+impl<T> ReaderFactory<T> {
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn reader(&self) -> SliceGuard<'_, T> {
+        self.vec.reader()
+    }
+
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn try_reader(&self) -> Result<SliceGuard<'_, T>, RwVecError> {
+        self.vec.try_reader()
+    }
+
+    pub fn version(&self) -> usize {
+        self.vec.version()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.vec.name()
+    }
+}
+
+impl<T : Clone> ReaderFactory<T> {
+    pub fn snapshot(&self) -> Snapshot<T> {
+        self.vec.snapshot()
+    }
+}
+
+impl<T> Clone for ReaderFactory<T> {
+    fn clone(&self) -> ReaderFactory<T> {
+        ReaderFactory { vec : self.vec.clone() }
+    }
+}
+
+//what watch_last() hands out
+pub struct WatchLast<T> {
+    vec  : Arc<RWVec<T>>,
+    //the push count as of the last changed()...anything past it is news
+    seen : usize
+}
+
+impl<T> WatchLast<T> {
+    //the newest element, or None while the vec is empty...holds a read lock like
+    //any other reader so don't keep it around across pushes that might realloc
+    #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+    pub fn borrow(&self) -> Option<LastGuard<'_, T>> {
+        let guard = self.vec.reader();
+
+        if guard.is_empty() {
+            None
+        } else {
+            Some(LastGuard { guard : guard })
+        }
+    }
+
+    //whether anything was pushed since the last changed()
+    pub fn has_changed(&self) -> bool {
+        self.vec.pushes.load(Ordering::SeqCst) != self.seen
+    }
+
+    //blocks until something is pushed after the last changed(), then marks it seen
+    pub fn changed(&mut self) {
+        let seen = self.seen;
+        self.vec.changed.wait_until(|| self.vec.pushes.load(Ordering::SeqCst) != seen);
+        self.seen = self.vec.pushes.load(Ordering::SeqCst);
+    }
+
+    //changed that gives up and returns false once timeout has passed
+    pub fn changed_timeout(&mut self, timeout : Duration) -> bool {
+        let seen = self.seen;
+        if !self.vec.changed.wait_until_timeout(timeout, || self.vec.pushes.load(Ordering::SeqCst) != seen) {
+            return false
+        }
+
+        self.seen = self.vec.pushes.load(Ordering::SeqCst);
+        true
+    }
+}
+
+impl<T> Clone for WatchLast<T> {
+    fn clone(&self) -> WatchLast<T> {
+        WatchLast { vec : self.vec.clone(), seen : self.seen }
+    }
+}
+
+//a read guard that derefs to the last element only
+pub struct LastGuard<'locked, T : 'locked> {
+    guard : SliceGuard<'locked, T>
+}
+
+impl<'locked, T> Deref for LastGuard<'locked, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        //borrow() only hands one out for a non-empty slice
+        &self.guard[self.guard.len() - 1]
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 ARRAY VEC                                 //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//a fixed capacity vec whose elements live inline, so it never touches the heap and
+//never reallocs...which means pushes never have to lock readers out at all. elements
+//only ever get appended through a shared reference, so a reader is just the slice up
+//to the len it saw and stays valid for as long as the vec is borrowed. can be a
+//static: static LOG : RWArrayVec<Entry, 256> = RWArrayVec::new();
+pub struct RWArrayVec<T, const N : usize> {
+    data    : UnsafeCell<[mem::MaybeUninit<T>; N]>,
+    //how many elements are published...everything below it is initialized
+    len     : AtomicUsize,
+    //one pusher at a time, spinning since the critical section is a single write
+    pushing : AtomicBool
+}
+
+unsafe impl<T : Send + Sync, const N : usize> Sync for RWArrayVec<T, N> { }
+
+impl<T, const N : usize> RWArrayVec<T, N> {
+    pub const fn new() -> RWArrayVec<T, N> {
+        RWArrayVec {
+            //an array of MaybeUninit doesn't need initializing
+            data    : UnsafeCell::new(unsafe { mem::MaybeUninit::<[mem::MaybeUninit<T>; N]>::uninit().assume_init() }),
+            len     : AtomicUsize::new(0),
+            pushing : AtomicBool::new(false)
+        }
+    }
+
+    //hands back the index t landed at, or t itself if all N slots are taken...elements
+    //only go away through &mut self, so there is nothing to wait for
+    pub fn push(&self, t : T) -> Result<usize, T> {
+        while self.pushing.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            std::hint::spin_loop();
+        }
+
+        let len = self.len.load(Ordering::Relaxed);
+        if len == N {
+            self.pushing.store(false, Ordering::Release);
+            return Err(t)
+        }
+
+        unsafe {
+            //past len, so no reader is looking at this slot
+            ptr::write((self.data.get() as *mut T).offset(len as isize), t);
+        }
+
+        //publish the element, then let the next pusher in
+        self.len.store(len + 1, Ordering::Release);
+        self.pushing.store(false, Ordering::Release);
+
+        Ok(len)
+    }
+
+    //everything pushed so far...no lock is needed since nothing below len ever changes
+    //while the vec is shared
+    pub fn reader(&self) -> &[T] {
+        let len = self.len.load(Ordering::Acquire);
+        unsafe { std::slice::from_raw_parts(self.data.get() as *const T, len) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn get_mut(&mut self) -> &mut [T] {
+        let len = *self.len.get_mut();
+        unsafe { std::slice::from_raw_parts_mut(self.data.get() as *mut T, len) }
+    }
+
+    //the only way elements go away...&mut self means nobody is reading or pushing
+    pub fn clear(&mut self) {
+        let len = mem::replace(self.len.get_mut(), 0);
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.data.get() as *mut T, len)); }
+    }
+}
+
+impl<T, const N : usize> Drop for RWArrayVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 BROADCAST                                 //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+impl<T : Clone + Send + 'static> RWVec<T> {
+    //a receiver that gets its own clone of every element pushed from now on, in push
+    //order. it buffers up to capacity of them...a receiver that falls further behind
+    //than that loses the oldest and is told how many on its next recv
+    pub fn broadcast(&self, capacity : usize) -> BroadcastReceiver<T> {
+        assert!(capacity > 0, "a broadcast receiver needs room for at least one element");
+
+        let queue = Arc::new(BroadcastQueue {
+            pending  : Mutex::new((VecDeque::with_capacity(capacity), 0)),
+            capacity : capacity,
+            closed   : AtomicBool::new(false),
+            arrived  : Condvar::new()
+        });
+
+        let sink = BroadcastSink { queue : Arc::downgrade(&queue) };
+        self.sinks.lock().unwrap().push(Box::new(move |t : &T| sink.offer(t)));
+        self.broadcasting.store(true, Ordering::SeqCst);
+
+        BroadcastReceiver { queue : queue }
+    }
+}
+
+//why recv didn't hand out an element
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BroadcastError {
+    //this many elements were dropped because the receiver fell behind...the next recv
+    //carries on with the oldest one still buffered
+    Lagged(usize),
+    //the vec is gone and everything buffered has been received
+    Closed
+}
+
+struct BroadcastQueue<T> {
+    //buffered elements and how many got dropped since the last recv
+    pending  : Mutex<(VecDeque<T>, usize)>,
+    capacity : usize,
+    closed   : AtomicBool,
+    arrived  : Condvar
+}
+
+//the vec's end of a receiver...only holds on weakly so a dropped receiver stops costing
+//clones, and tells the receiver when the vec drops it
+struct BroadcastSink<T> {
+    queue : std::sync::Weak<BroadcastQueue<T>>
+}
+
+impl<T : Clone> BroadcastSink<T> {
+    fn offer(&self, t : &T) -> bool {
+        let queue = match self.queue.upgrade() {
+            Some(queue) => queue,
+            None        => return false
+        };
+
+        let mut pending = queue.pending.lock().unwrap();
+        if pending.0.len() == queue.capacity {
+            pending.0.pop_front();
+            pending.1 += 1;
+        }
+        pending.0.push_back(t.clone());
+        queue.arrived.notify_all();
+
+        true
+    }
+}
+
+impl<T> Drop for BroadcastSink<T> {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.upgrade() {
+            let _pending = queue.pending.lock().unwrap();
+            queue.closed.store(true, Ordering::SeqCst);
+            queue.arrived.notify_all();
+        }
+    }
+}
+
+pub struct BroadcastReceiver<T> {
+    queue : Arc<BroadcastQueue<T>>
+}
+
+impl<T> BroadcastReceiver<T> {
+    //blocks until the next element is pushed
+    pub fn recv(&self) -> Result<T, BroadcastError> {
+        let mut pending = self.queue.pending.lock().unwrap();
+
+        loop {
+            if let Some(error) = BroadcastReceiver::check(&self.queue, &mut pending) {
+                return error
+            }
+
+            pending = self.queue.arrived.wait(pending).unwrap();
+        }
+    }
+
+    //None if nothing has been pushed since the last recv
+    pub fn try_recv(&self) -> Option<Result<T, BroadcastError>> {
+        let mut pending = self.queue.pending.lock().unwrap();
+        BroadcastReceiver::check(&self.queue, &mut pending)
+    }
+
+    fn check(queue : &BroadcastQueue<T>, pending : &mut (VecDeque<T>, usize)) -> Option<Result<T, BroadcastError>> {
+        if pending.1 != 0 {
+            return Some(Err(BroadcastError::Lagged(mem::replace(&mut pending.1, 0))))
+        }
+
+        match pending.0.pop_front() {
+            Some(t) => Some(Ok(t)),
+            None if queue.closed.load(Ordering::SeqCst) => Some(Err(BroadcastError::Closed)),
+            None => None
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  EXPIRY                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//an element stamped with when it was pushed, for vecs whose contents go stale
+pub struct Timed<T> {
+    at    : Instant,
+    value : T
+}
+
+impl<T> Timed<T> {
+    pub fn pushed_at(&self) -> Instant {
+        self.at
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Timed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> RWVec<Timed<T>> {
+    //stamps t inside the push lock, so the stamps are in the same order as the elements
+    //and expire only ever has to look at the front
+    pub fn push_timed(&self, t : T) -> usize {
+        self.push_with(move |_| Timed { at : Instant::now(), value : t })
+    }
+
+    //drops everything pushed more than ttl ago and says how many went...meant to be
+    //called from whatever already does maintenance, or before reading. readers are
+    //only locked out if the oldest element has actually expired, and since that waits
+    //out every live SliceGuard nobody sees elements vanish from under them
+    pub fn expire(&self, ttl : Duration) -> usize {
+        let stale = |timed : &Timed<T>| timed.at.elapsed() >= ttl;
+
+        let any = self.reader().first().is_some_and(stale);
+        if !any {
+            return 0
+        }
+
+        let mut vec = self.exclusive();
+        let expired = vec.iter().take_while(|timed| stale(timed)).count();
+        vec.drain(..expired);
+        self.release_drained(&mut vec);
+
+        expired
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                   RAYON                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "rayon")]
+impl<T : Send> RWVec<T> {
+    //pushes everything iter produces...each rayon worker fills a plain vec of its own
+    //and hands it over with one append, so the push lock is taken once per worker batch
+    //rather than once per element. batches land in whatever order the workers finish
+    //them, so the elements are only in iter's order within a batch
+    pub fn par_extend<I>(&self, iter : I) where I : rayon::iter::IntoParallelIterator<Item = T> {
+        use rayon::iter::ParallelIterator;
+
+        iter.into_par_iter()
+            .fold(std::vec::Vec::new, |mut batch, t| { batch.push(t); batch })
+            .for_each(|mut batch| self.append(&mut batch));
+    }
+}
+
+//par_iter().map(...).collect::<RWVec<T>>(), through par_extend
+#[cfg(feature = "rayon")]
+impl<T : Send> rayon::iter::FromParallelIterator<T> for RWVec<T> {
+    fn from_par_iter<I>(iter : I) -> RWVec<T> where I : rayon::iter::IntoParallelIterator<Item = T> {
+        let vec = RWVec::from_builder(RWVec::builder());
+        vec.par_extend(iter);
+        vec
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                JOINT READS                                //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//read guards on several vecs that were all held at the same time, so there is one
+//moment at which every one of them showed exactly what their guards show...e.g.
+//let (names, ages) = snapshot_together((&*names, &*ages));
+//every push lock is held while the ends are read, so a push can't land on one vec
+//between reading its end and the next one's. the locks are taken in address order
+//(push locks first, like refresh) without waiting on a read lock while holding any
+//other, so it can't deadlock against writers that lock several vecs themselves. the
+//calling thread shouldn't hold guards on any of the vecs already, and every vec has
+//to be a different one (it panics otherwise, the push locks don't nest)
+#[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+pub fn snapshot_together<'locked, V>(vecs : V) -> V::Guards where V : ReadTogether<'locked> {
+    vecs.read_together()
+}
+
+//tuples of vecs snapshot_together can take
+pub trait ReadTogether<'locked> {
+    type Guards;
+
+    fn read_together(self) -> Self::Guards;
+}
+
+//takes every push lock and then a read lock on every lock that needs one (zero sized
+//elements don't), returning what the reads were taken through in the order given. the
+//push locks come back too, so the caller can read the ends before dropping them.
+//whenever a read lock can't be had right away the reads are let go again until its
+//writer is done...writers never wait on a push lock while holding the write lock, so
+//keeping the push locks through that is fine
+fn read_all<'locked>(locks : &[(&'locked ResizeLock, &'locked PushLock, bool)])
+    -> (std::vec::Vec<ReadSlot>, std::vec::Vec<PushHold<'locked>>)
+{
+    let mut pushes : std::vec::Vec<usize> = (0..locks.len()).collect();
+    pushes.sort_by_key(|&i| locks[i].0 as *const ResizeLock as usize);
+
+    //the second push lock on the same vec would wait on ourselves forever
+    assert!(pushes.windows(2).all(|pair| !ptr::eq(locks[pair[0]].0, locks[pair[1]].0)), "snapshot_together: the same vec was passed more than once");
+
+    let mut held = std::vec::Vec::with_capacity(locks.len());
+    for &i in pushes.iter() {
+        locks[i].1.acquire(locks[i].0.backend);
+        held.push(PushHold { lock : locks[i].1 });
+    }
+
+    let order : std::vec::Vec<usize> = pushes.into_iter().filter(|&i| !locks[i].2).collect();
+    let mut reads : std::vec::Vec<ReadSlot> = locks.iter().map(|_| ReadSlot::none()).collect();
+
+    'retry: loop {
+        for (taken, &i) in order.iter().enumerate() {
+            match unsafe { locks[i].0.try_read_now() } {
+                Some(slot) => reads[i] = slot,
+                None       => {
+                    for &j in order[..taken].iter() {
+                        unsafe { locks[j].0.read_unlock(&reads[j]); }
+                    }
+
+                    locks[i].0.wait_for_writer();
+                    continue 'retry
+                }
+            }
+        }
+
+        return (reads, held)
+    }
+}
+
+macro_rules! read_together_tuple {
+    ($($T:ident $vec:ident),+) => {
+        impl<'locked, $($T),+> ReadTogether<'locked> for ($(&'locked RWVec<$T>,)+) {
+            type Guards = ($(SliceGuard<'locked, $T>,)+);
+
+            #[cfg_attr(any(feature = "watchdog", feature = "debug-locks"), track_caller)]
+            fn read_together(self) -> ($(SliceGuard<'locked, $T>,)+) {
+                let ($($vec,)+) = self;
+                $($vec.check_poison();)+
+
+                let (reads, pushes) = read_all(&[$((&$vec.rw_lock, &$vec.push_lock, RWVec::<$T>::is_zst())),+]);
+                let mut reads = reads.into_iter();
+                let guards = ($(SliceGuard::locked($vec, reads.next().unwrap()),)+);
+                drop(pushes);
+                guards
+            }
+        }
+    }
+}
+
+read_together_tuple!(A a);
+read_together_tuple!(A a, B b);
+read_together_tuple!(A a, B b, C c);
+read_together_tuple!(A a, B b, C c, D d);
+read_together_tuple!(A a, B b, C c, D d, E e);
+read_together_tuple!(A a, B b, C c, D d, E e, F f);
+read_together_tuple!(A a, B b, C c, D d, E e, F f, G g);
+read_together_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  COLUMNS                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//a struct stored column-wise: one RWVec per field, so a scan over one field only
+//pulls that field through the cache...e.g.
+//rwvec_columns! { struct Points / PointsGuard for Point { x : f32, y : f32 } }
+//gives Points::new(), points.push(Point { .. }) and points.reader().x() -> &[f32].
+//rows go in one at a time under pushing, first column first, so a column can only
+//ever be ahead of the ones after it. reader() locks every column at once (like
+//snapshot_together) and cuts them all down to the rows that are complete in every
+//one of them, so the slices always line up. there is no way to push to a single
+//column or take rows out, which is what keeps that true. up to 8 columns, as many as
+//snapshot_together takes
+#[macro_export]
+macro_rules! rwvec_columns {
+    (struct $name:ident / $guard:ident for $row:ident { $($field:ident : $T:ty),+ $(,)* }) => {
+        struct $name {
+            $($field : ::std::sync::Arc<$crate::RWVec<$T>>,)+
+            //one row at a time, so the columns never disagree on what a row is
+            pushing : ::std::sync::Mutex<()>
+        }
+
+        //a read guard per column, all taken together and cut to the same rows
+        struct $guard<'locked> {
+            $($field : $crate::SliceGuard<'locked, $T>,)+
+            rows : usize
+        }
+
+        impl $name {
+            pub fn new() -> ::std::sync::Arc<$name> {
+                ::std::sync::Arc::new($name {
+                    $($field : $crate::RWVec::new(),)+
+                    pushing : ::std::sync::Mutex::new(())
+                })
+            }
+
+            pub fn push(&self, row : $row) {
+                let _pushing = self.pushing.lock().unwrap();
+                $(self.$field.push(row.$field);)+
+            }
+
+            //rows that are in every column
+            pub fn len(&self) -> usize {
+                self.reader().len()
+            }
+
+            pub fn reader(&self) -> $guard<'_> {
+                let ($($field,)+) = $crate::snapshot_together(($(&*self.$field,)+));
+
+                let mut rows = usize::MAX;
+                $(rows = ::std::cmp::min(rows, $field.len());)+
+
+                $guard {
+                    $($field : $field.clipped(rows),)+
+                    rows : rows
+                }
+            }
+        }
+
+        impl<'locked> $guard<'locked> {
+            $(
+                pub fn $field(&self) -> &[$T] {
+                    &self.$field
+                }
+            )+
+
+            pub fn len(&self) -> usize {
+                self.rows
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                   RAND                                    //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "rand")]
+impl<T> RWVec<T> {
+    //puts the elements in a random order...they only change places, so like rotate_left
+    //this just takes the write lock
+    pub fn shuffle<R>(&self, rng : &mut R) where R : rand::Rng + ?Sized {
+        use rand::seq::SliceRandom;
+
+        self.writer().shuffle(rng);
+    }
+}
+
+#[cfg(feature = "rand")]
+impl<T : Clone> RWVec<T> {
+    //clones out k elements picked uniformly at random (all of them if there are fewer),
+    //without repeats...the read lock is only held while picking and cloning
+    pub fn sample<R>(&self, rng : &mut R, k : usize) -> std::vec::Vec<T> where R : rand::Rng + ?Sized {
+        use rand::seq::SliceRandom;
+
+        self.reader().choose_multiple(rng, k).cloned().collect()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                AGGREGATES                                 //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//plain numbers, for the aggregates below...the scans work on whole slices so the simd
+//feature can swap in versions that go a vector at a time, see numeric!
+pub trait Numeric : Copy + PartialOrd + std::iter::Sum<Self> {
+    fn as_f64(self) -> f64;
+
+    fn sum_of(elements : &[Self]) -> Self {
+        elements.iter().cloned().sum()
+    }
+
+    fn min_of(elements : &[Self]) -> Option<Self> {
+        elements.iter().cloned().fold(None, |min, t| pick(min, t, |t, min| t < min))
+    }
+
+    fn max_of(elements : &[Self]) -> Option<Self> {
+        elements.iter().cloned().fold(None, |max, t| pick(max, t, |t, max| t > max))
+    }
+
+    fn position_of(elements : &[Self], value : Self) -> Option<usize> {
+        elements.iter().position(|&t| t == value)
+    }
+}
+
+//t if it beats best, skipping anything that doesn't compare to itself (NaN)
+fn pick<T, F>(best : Option<T>, t : T, beats : F) -> Option<T> where T : PartialOrd, F : FnOnce(&T, &T) -> bool {
+    if t.partial_cmp(&t).is_none() {
+        return best
+    }
+
+    match best {
+        Some(best) => if beats(&t, &best) { Some(t) } else { Some(best) },
+        None       => Some(t)
+    }
+}
+
+#[cfg(not(feature = "simd"))]
+macro_rules! numeric {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                fn as_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    }
+}
+
+//how many elements the simd scans take at a time
+#[cfg(feature = "simd")]
+const SIMD_LANES : usize = 16;
+
+//the slices get split into an unaligned head and tail done one element at a time and
+//a body of whole vectors in between. integer sums wrap in the vector part
+#[cfg(feature = "simd")]
+macro_rules! numeric {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                fn as_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn sum_of(elements : &[$t]) -> $t {
+                    use std::simd::prelude::*;
+
+                    let (head, body, tail) = elements.as_simd::<SIMD_LANES>();
+                    let lanes = body.iter().fold(Simd::splat(0 as $t), |sum, &v| sum + v);
+                    head.iter().chain(tail).cloned().sum::<$t>() + lanes.reduce_sum()
+                }
+
+                fn min_of(elements : &[$t]) -> Option<$t> {
+                    use std::simd::prelude::*;
+
+                    let (head, body, tail) = elements.as_simd::<SIMD_LANES>();
+                    let mut min = head.iter().chain(tail).cloned().fold(None, |min, t| pick(min, t, |t, min| t < min));
+                    if let Some((&first, rest)) = body.split_first() {
+                        min = pick(min, rest.iter().fold(first, |min, &v| min.simd_min(v)).reduce_min(), |t, min| t < min);
+                    }
+
+                    min
+                }
+
+                fn max_of(elements : &[$t]) -> Option<$t> {
+                    use std::simd::prelude::*;
+
+                    let (head, body, tail) = elements.as_simd::<SIMD_LANES>();
+                    let mut max = head.iter().chain(tail).cloned().fold(None, |max, t| pick(max, t, |t, max| t > max));
+                    if let Some((&first, rest)) = body.split_first() {
+                        max = pick(max, rest.iter().fold(first, |max, &v| max.simd_max(v)).reduce_max(), |t, max| t > max);
+                    }
+
+                    max
+                }
+
+                fn position_of(elements : &[$t], value : $t) -> Option<usize> {
+                    use std::simd::prelude::*;
+
+                    let (head, body, tail) = elements.as_simd::<SIMD_LANES>();
+                    if let Some(i) = head.iter().position(|&t| t == value) {
+                        return Some(i)
+                    }
+
+                    let wanted = Simd::splat(value);
+                    for (i, v) in body.iter().enumerate() {
+                        let hits = v.simd_eq(wanted);
+                        if hits.any() {
+                            return Some(head.len() + i * SIMD_LANES + hits.to_bitmask().trailing_zeros() as usize)
+                        }
+                    }
+
+                    tail.iter().position(|&t| t == value).map(|i| head.len() + body.len() * SIMD_LANES + i)
+                }
+            }
+        )*
+    }
+}
+
+numeric!(usize, isize, u64, i64, u32, i32, u16, i16, u8, i8, f64, f32);
+
+//each of these reads one snapshot and lets go of it before returning
+impl<T : Numeric> RWVec<T> {
+    //overflows like summing the elements by hand would
+    pub fn sum(&self) -> T {
+        T::sum_of(&self.reader())
+    }
+
+    //None if the vec is empty (or only holds NaNs)
+    pub fn min(&self) -> Option<T> {
+        T::min_of(&self.reader())
+    }
+
+    pub fn max(&self) -> Option<T> {
+        T::max_of(&self.reader())
+    }
+
+    //summed as f64, so it doesn't overflow where sum would
+    pub fn mean(&self) -> Option<f64> {
+        let reader = self.reader();
+        if reader.is_empty() {
+            return None
+        }
+
+        let total : f64 = reader.iter().map(|t| t.as_f64()).sum();
+        Some(total / reader.len() as f64)
+    }
+}
+
+//the same scans over a guard that is already held
+impl<'locked, T : Numeric> SliceGuard<'locked, T> {
+    pub fn contains(&self, value : &T) -> bool {
+        T::position_of(self, *value).is_some()
+    }
+
+    pub fn position(&self, value : T) -> Option<usize> {
+        T::position_of(self, value)
+    }
+
+    pub fn sum(&self) -> T {
+        T::sum_of(self)
+    }
+
+    pub fn min(&self) -> Option<T> {
+        T::min_of(self)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        T::max_of(self)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  ZEROIZE                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//every buffer the vec lets go of goes through here, so with the zeroize feature none
+//of them goes back to the allocator with element bytes still in it...that covers
+//reallocs, shrinks and the vec being dropped. whatever is done to the Vec inside an
+//exclusive section (a VecGuardMut) is up to the caller, and so are elements once
+//they have been moved out
+
+//try_reserve, except the old buffer is wiped before being freed if it has to move
+fn reserve_wiping<T>(vec : &mut std::vec::Vec<T>, additional : usize) -> Result<(), TryReserveError> {
+    #[cfg(feature = "zeroize")]
+    {
+        if vec.capacity() - vec.len() >= additional {
+            return Ok(())
+        }
+
+        //the same doubling Vec would do
+        let wanted = match vec.len().checked_add(additional) {
+            Some(wanted) => std::cmp::max(wanted, vec.capacity() * 2),
+            None         => return vec.try_reserve(additional)
+        };
+
+        move_wiping(vec, wanted)
+    }
+
+    #[cfg(not(feature = "zeroize"))]
+    {
+        vec.try_reserve(additional)
+    }
+}
+
+//shrink_to, wiping the old buffer the same way
+fn shrink_wiping<T>(vec : &mut std::vec::Vec<T>, min_capacity : usize) {
+    #[cfg(feature = "zeroize")]
+    {
+        let target = std::cmp::max(vec.len(), min_capacity);
+        if target < vec.capacity() {
+            //keeping the bigger buffer is fine if there's no memory for a smaller one
+            let _ = move_wiping(vec, target);
+        }
+    }
+
+    #[cfg(not(feature = "zeroize"))]
+    {
+        vec.shrink_to(min_capacity);
+    }
+}
+
+//frees a buffer whose elements have already been moved out or dropped (len 0)
+fn free_wiping<T>(vec : std::vec::Vec<T>) {
+    #[cfg(feature = "zeroize")]
+    {
+        use zeroize::Zeroize;
+
+        let mut vec = vec;
+        unsafe {
+            let bytes = vec.capacity() * mem::size_of::<T>();
+            std::slice::from_raw_parts_mut(vec.as_mut_ptr() as *mut u8, bytes).zeroize();
+        }
+        drop(vec);
+    }
+
+    #[cfg(not(feature = "zeroize"))]
+    {
+        drop(vec);
+    }
+}
+
+//moves the elements into a fresh buffer of exactly capacity, then wipes and frees the
+//old one
+#[cfg(feature = "zeroize")]
+fn move_wiping<T>(vec : &mut std::vec::Vec<T>, capacity : usize) -> Result<(), TryReserveError> {
+    let mut next = std::vec::Vec::new();
+    next.try_reserve_exact(capacity)?;
+
+    unsafe {
+        ptr::copy_nonoverlapping(vec.as_ptr(), next.as_mut_ptr(), vec.len());
+        next.set_len(vec.len());
+        vec.set_len(0);
+    }
+
+    mem::swap(vec, &mut next);
+    free_wiping(next);
+    Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                             COMPRESSED EXPORT                             //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//the format is the element count followed by every element, bincode encoded and the
+//whole stream run through zstd
+
+#[cfg(all(feature = "serde", feature = "zstd"))]
+impl<T : serde::Serialize> RWVec<T> {
+    //streams one consistent snapshot into writer, encoding straight out of a read guard
+    //so nothing gets copied first...the read lock is held until the whole thing has
+    //been written, which keeps out writers and pushers that need to realloc. level is
+    //zstd's, 0 for its default. hands back writer once the stream is finished
+    pub fn export_compressed<W>(&self, writer : W, level : i32) -> io::Result<W> where W : io::Write {
+        let mut encoder = zstd::stream::Encoder::new(writer, level)?;
+        let reader      = self.reader();
+
+        bincode::serialize_into(&mut encoder, &(reader.len() as u64)).map_err(export_error)?;
+        for t in reader.iter() {
+            bincode::serialize_into(&mut encoder, t).map_err(export_error)?;
+        }
+
+        encoder.finish()
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "zstd"))]
+impl<T : serde::de::DeserializeOwned> RWVec<T> {
+    //reads back what export_compressed wrote into a new vec
+    pub fn import_compressed<R>(reader : R) -> io::Result<Arc<RWVec<T>>> where R : io::Read {
+        let mut decoder = zstd::stream::Decoder::new(reader)?;
+
+        let len : u64 = bincode::deserialize_from(&mut decoder).map_err(export_error)?;
+        let mut elements = std::vec::Vec::new();
+        for _ in 0..len {
+            elements.push(bincode::deserialize_from(&mut decoder).map_err(export_error)?);
+        }
+
+        Ok(RWVec::from_vec(elements))
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "zstd"))]
+fn export_error(error : bincode::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                FLATBUFFERS                                //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//for element types flatbuffers can push (scalars and structs from a schema), a snapshot
+//becomes a vector in the message, written straight out of the read guard without an
+//intermediate buffer. flatbuffers still writes it element by element, little endian
+#[cfg(feature = "flatbuffers")]
+impl<T : flatbuffers::Push + Copy> RWVec<T> {
+    //the offset goes wherever the schema wants the vector, e.g. into the table builder
+    pub fn export_flatbuffer<'fbb>(&self, builder : &mut flatbuffers::FlatBufferBuilder<'fbb>) -> flatbuffers::WIPOffset<flatbuffers::Vector<'fbb, T::Output>> {
+        builder.create_vector(&self.reader())
+    }
+}
+
+#[cfg(feature = "flatbuffers")]
+impl<T : Copy> RWVec<T> {
+    //a new vec holding what a message's vector holds...the message isn't copied first,
+    //its elements are read out one at a time into the new buffer
+    pub fn import_flatbuffer<'buf>(vector : flatbuffers::Vector<'buf, T>) -> Arc<RWVec<T>> where T : flatbuffers::Follow<'buf, Inner = T> {
+        RWVec::from_vec(vector.iter().collect())
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                   ARROW                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//primitive vecs as arrow arrays, copied straight out of a read guard into the arrow
+//buffer so the data is only copied once. P names the arrow type of the elements, e.g.
+//vec.to_arrow::<Float64Type>()
+#[cfg(feature = "arrow")]
+impl<T : arrow::datatypes::ArrowNativeType> RWVec<T> {
+    pub fn to_arrow<P>(&self) -> arrow::array::PrimitiveArray<P> where P : arrow::datatypes::ArrowPrimitiveType<Native = T> {
+        arrow::array::PrimitiveArray::<P>::from_iter_values(self.reader().iter().cloned())
+    }
+
+    //the same array as the only column of a record batch, for handing to query engines
+    pub fn to_record_batch<P>(&self, column : &str) -> Result<arrow::record_batch::RecordBatch, arrow::error::ArrowError> where P : arrow::datatypes::ArrowPrimitiveType<Native = T> {
+        let array : arrow::array::ArrayRef = Arc::new(self.to_arrow::<P>());
+        arrow::record_batch::RecordBatch::try_from_iter(vec![(column, array)])
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  TAILER                                   //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//how a tailer writes each element
+#[cfg(any(feature = "json", feature = "csv"))]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TailFormat {
+    //one JSON document per line
+    #[cfg(feature = "json")]
+    JsonLines,
+    //one record per line, no header
+    #[cfg(feature = "csv")]
+    Csv
+}
+
+#[cfg(any(feature = "json", feature = "csv"))]
+impl<T : serde::Serialize + Send + 'static> RWVec<T> {
+    //writes every element appended from now on to sink, in order, from a thread of its
+    //own that sleeps until the vec changes...an audit trail with no plumbing. elements
+    //are encoded under the read lock but sink is only written to after it is let go.
+    //if the vec shrinks below what was written the tailer carries on from the new end
+    pub fn tail<W>(vec : &Arc<RWVec<T>>, sink : W, format : TailFormat) -> Tailer<W> where W : io::Write + Send + 'static {
+        let stop   = Arc::new(AtomicBool::new(false));
+        //from now on means from here, not from whenever the thread gets going
+        let from   = vec.visible_len();
+        let thread = {
+            let vec  = vec.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || tail_into(&vec, from, sink, format, &stop))
+        };
+
+        Tailer {
+            wake   : Box::new({
+                let vec = vec.clone();
+                move || vec.changed.notify()
+            }),
+            stop   : stop,
+            thread : Some(thread)
+        }
+    }
+}
+
+#[cfg(any(feature = "json", feature = "csv"))]
+fn tail_into<T, W>(vec : &RWVec<T>, from : usize, mut sink : W, format : TailFormat, stop : &AtomicBool) -> io::Result<W> where T : serde::Serialize, W : io::Write {
+    let mut written = from;
+    let mut encoded = std::vec::Vec::new();
+
+    loop {
+        let seen = vec.pushes.load(Ordering::SeqCst);
+
+        {
+            let reader = vec.reader();
+            written = std::cmp::min(written, reader.len());
+            for t in reader[written..].iter() {
+                encode(&mut encoded, t, format)?;
+            }
+            written = reader.len();
+        }
+
+        if !encoded.is_empty() {
+            sink.write_all(&encoded)?;
+            sink.flush()?;
+            encoded.clear();
+        }
+
+        if stop.load(Ordering::SeqCst) {
+            return Ok(sink)
+        }
+
+        vec.changed.wait_until(|| stop.load(Ordering::SeqCst) || vec.pushes.load(Ordering::SeqCst) != seen || vec.visible_len() != written);
+    }
+}
+
+#[cfg(any(feature = "json", feature = "csv"))]
+fn encode<T : serde::Serialize>(out : &mut std::vec::Vec<u8>, t : &T, format : TailFormat) -> io::Result<()> {
+    match format {
+        #[cfg(feature = "json")]
+        TailFormat::JsonLines => {
+            serde_json::to_writer(&mut *out, t).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            out.push(b'\n');
+        },
+        #[cfg(feature = "csv")]
+        TailFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(&mut *out);
+            writer.serialize(t).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+//the handle tail() gives back...dropping it stops the thread after it has caught up
+#[cfg(any(feature = "json", feature = "csv"))]
+pub struct Tailer<W> {
+    //nudges the thread so it sees stop without the vec changing
+    wake   : Box<dyn Fn() + Send>,
+    stop   : Arc<AtomicBool>,
+    thread : Option<std::thread::JoinHandle<io::Result<W>>>
+}
+
+#[cfg(any(feature = "json", feature = "csv"))]
+impl<W> Tailer<W> {
+    //writes out whatever has been appended so far, then hands back the sink...or the
+    //error that stopped the tailer early
+    pub fn stop(mut self) -> io::Result<W> {
+        self.finish().expect("tailer thread panicked")
+    }
+
+    fn finish(&mut self) -> Option<io::Result<W>> {
+        self.stop.store(true, Ordering::SeqCst);
+        (self.wake)();
+        self.thread.take().and_then(|thread| thread.join().ok())
+    }
+}
+
+#[cfg(any(feature = "json", feature = "csv"))]
+impl<W> Drop for Tailer<W> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  METRICS                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "metrics")]
+impl<T> RWVec<T> {
+    //hands the current numbers to whatever recorder is installed for the metrics crate
+    //(e.g. the prometheus exporter), labelled with the vec's name...call it wherever
+    //the rest of the process' metrics get refreshed. guard hold times are recorded as
+    //the guards drop. unnamed vecs have nothing to tell them apart so they are left out
+    pub fn publish_metrics(&self) {
+        let name = match self.name {
+            Some(ref name) => name.clone(),
+            None           => return
+        };
+
+        let stats = self.stats();
+
+        gauge!("rwvec_len", "vec" => name.clone()).set(self.visible_len() as f64);
+        gauge!("rwvec_capacity", "vec" => name.clone()).set(unsafe { self.vec().capacity() } as f64);
+        counter!("rwvec_pushes_total", "vec" => name.clone()).absolute(stats.pushes as u64);
+        counter!("rwvec_realloc_pushes_total", "vec" => name.clone()).absolute(stats.realloc_pushes as u64);
+        counter!("rwvec_contended_pushes_total", "vec" => name).absolute(stats.contended_pushes as u64);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                  STORAGE                                  //
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+/// The lock choreography the guards above are built on, for other collections that
+/// want to be read through snapshots the same way...a map, a graph, an arena. The
+/// collection keeps a ResizeLock next to its contents and the snapshots below do the
+/// locking.
+///
+/// # Safety
+///
+/// Implementing it promises that the contents only change while the write lock is
+/// held, or only in places readers never look (the way RWVec's pushers write past the
+/// end every reader sliced at).
+pub unsafe trait RwStorage {
+    type Target : ?Sized;
+
+    fn resize_lock(&self) -> &ResizeLock;
+
+    /// # Safety
+    ///
+    /// Only to be called with at least a read lock on resize_lock() held, for no
+    /// longer than that lock is.
+    unsafe fn contents(&self) -> &Self::Target;
+
+    /// # Safety
+    ///
+    /// Only to be called with the write lock on resize_lock() held, for no longer than
+    /// that lock is. The write lock is what keeps the &mut from aliasing.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn contents_mut(&self) -> &mut Self::Target;
+}
+
+//RWVec's own guards carry more (watchdog tickets, epochs, the byte budget...) so they
+//don't go through this, but a vec can still be handed to anything written against it.
+//readers get what a reader() would show
+unsafe impl<T> RwStorage for RWVec<T> {
+    type Target = [T];
+
+    fn resize_lock(&self) -> &ResizeLock {
+        &self.rw_lock
+    }
+
+    //built from the pointer like SliceGuard's view, zero sized elements never make it
+    //into the vec's own len
+    unsafe fn contents(&self) -> &[T] {
+        std::slice::from_raw_parts(self.vec().as_ptr(), self.visible_len())
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn contents_mut(&self) -> &mut [T] {
+        let end = self.visible_len();
+        std::slice::from_raw_parts_mut(self.vec().as_mut_ptr(), end)
+    }
+}
+
+//a read lock on some storage, derefs to its contents as they were when the lock was
+//taken (or last refreshed)...like SliceGuard's end, so pushers writing past it don't
+//show up halfway through a read
+pub struct ReadSnapshot<'locked, S : RwStorage + ?Sized + 'locked> {
+    storage    : &'locked S,
+    read       : ReadSlot,
+    contents   : *const S::Target,
+    //the write lock generation when the read lock was taken
+    generation : usize,
+    //holds the upgradable gate as well, see upgradable()
+    upgradable : bool
+}
+
+impl<'locked, S : RwStorage + ?Sized> ReadSnapshot<'locked, S> {
+    pub fn new(storage : &'locked S) -> ReadSnapshot<'locked, S> {
+        let read = unsafe { storage.resize_lock().read() };
+
+        ReadSnapshot {
+            storage    : storage,
+            read       : read,
+            contents   : unsafe { storage.contents() },
+            generation : storage.resize_lock().generation(),
+            upgradable : false
+        }
+    }
+
+    //a snapshot that can be upgraded without anything changing in between...writers
+    //and other upgradable snapshots wait for it the way they do for an UpgradableGuard
+    pub fn upgradable(storage : &'locked S) -> ReadSnapshot<'locked, S> {
+        unsafe { storage.resize_lock().enter_upgradable(); }
+
+        let mut snapshot = ReadSnapshot::new(storage);
+        snapshot.upgradable = true;
+        snapshot
+    }
+
+    //lets writers through and takes the read lock again...true if any of them got in
+    //and might have changed what this derefs to
+    pub fn refresh(&mut self) -> bool {
+        let lock = self.storage.resize_lock();
+
+        unsafe {
+            lock.read_unlock(&self.read);
+            self.read     = lock.read();
+            self.contents = self.storage.contents();
+        }
+
+        let generation = lock.generation();
+        mem::replace(&mut self.generation, generation) != generation
+    }
+
+    //turns into a write lock. an upgradable snapshot does it without letting go, a plain
+    //one has to drop its read lock first so a writer may slip in...the flag says whether
+    //one did (never for an upgradable snapshot)
+    pub fn upgrade(self) -> (WriteSnapshot<'locked, S>, bool) {
+        let storage    = self.storage;
+        let lock       = storage.resize_lock();
+        let read       = unsafe { ptr::read(&self.read) };
+        let generation = self.generation;
+        let upgradable = self.upgradable;
+        mem::forget(self);
+
+        unsafe {
+            if upgradable {
+                //the gate stays taken, write_unlock releases it with the write lock
+                lock.begin_upgrade();
+                lock.read_unlock(&read);
+                lock.finish_upgrade();
+            } else {
+                lock.read_unlock(&read);
+                lock.write();
+            }
+        }
+
+        (WriteSnapshot { storage : storage }, lock.generation() != generation)
+    }
+}
+
+impl<'locked, S : RwStorage + ?Sized> Deref for ReadSnapshot<'locked, S> {
+    type Target = S::Target;
+
+    fn deref<'a>(&'a self) -> &'a S::Target {
+        unsafe { &*self.contents }
+    }
+}
+
+impl<'locked, S : RwStorage + ?Sized> Drop for ReadSnapshot<'locked, S> {
+    fn drop(&mut self) {
+        let lock = self.storage.resize_lock();
+
+        unsafe {
+            lock.read_unlock(&self.read);
+            if self.upgradable {
+                lock.leave_upgradable();
+            }
+        }
+    }
+}
+
+//the write lock on some storage, derefs (mutably) to its contents
+pub struct WriteSnapshot<'locked, S : RwStorage + ?Sized + 'locked> {
+    storage : &'locked S
+}
+
+impl<'locked, S : RwStorage + ?Sized> WriteSnapshot<'locked, S> {
+    pub fn new(storage : &'locked S) -> WriteSnapshot<'locked, S> {
+        unsafe { storage.resize_lock().write(); }
+
+        WriteSnapshot { storage : storage }
+    }
+}
+
+impl<'locked, S : RwStorage + ?Sized> Deref for WriteSnapshot<'locked, S> {
+    type Target = S::Target;
+
+    fn deref<'a>(&'a self) -> &'a S::Target {
+        unsafe { self.storage.contents() }
+    }
+}
+
+impl<'locked, S : RwStorage + ?Sized> DerefMut for WriteSnapshot<'locked, S> {
+    fn deref_mut<'a>(&'a mut self) -> &'a mut S::Target {
+        unsafe { self.storage.contents_mut() }
+    }
+}
+
+impl<'locked, S : RwStorage + ?Sized> Drop for WriteSnapshot<'locked, S> {
+    fn drop(&mut self) {
+        unsafe { self.storage.resize_lock().write_unlock(); }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                                                           //
+//                                 TESTS                                     //                               
+//                                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+//the hot fields each start a cache line and fill it, so no two of them (and nothing
+//else) ever share one
+#[test]
+fn hot_fields_on_own_cache_lines() {
+    let vec = RWVec::<u32>::new();
+
+    let hot = [
+        (&vec.push_lock as *const _ as usize, mem::size_of_val(&vec.push_lock)),
+        (&vec.rw_lock.writer as *const _ as usize, mem::size_of_val(&vec.rw_lock.writer)),
+        (&vec.rw_lock.readers[0] as *const _ as usize, mem::size_of_val(&vec.rw_lock.readers[0])),
+        (&vec.pushes as *const _ as usize, mem::size_of_val(&vec.pushes)),
+        (&vec.zst_len as *const _ as usize, mem::size_of_val(&vec.zst_len))
+    ];
+
+    for &(address, size) in hot.iter() {
+        assert_eq!(address % 64, 0);
+        assert_eq!(size % 64, 0);
+    }
+
+    for (i, &(a, _)) in hot.iter().enumerate() {
+        for &(b, _) in hot[i + 1..].iter() {
+            assert!(a != b);
+        }
+    }
+}
+
+//holds b's write lock on another thread until told to let go
+#[cfg(test)]
+fn hold_writer<T : Send + 'static>(vec : &Arc<RWVec<T>>) -> (mpsc::Receiver<()>, mpsc::Sender<()>, std::thread::JoinHandle<()>) {
+    let (locked_tx, locked) = mpsc::channel();
+    let (release, release_rx) = mpsc::channel::<()>();
+    let vec = vec.clone();
+
+    let writer = std::thread::spawn(move || {
+        let _writer = vec.writer();
+        locked_tx.send(()).unwrap();
+        release_rx.recv().unwrap();
+    });
+
+    (locked, release, writer)
+}
+
+//a read lock on one vec is no reason to walk past the writer of another
+#[test]
+fn nested_read_on_other_vec_waits_for_writer() {
+    let a = rwvec![1, 2, 3];
+    let b = rwvec![4, 5, 6];
+
+    let _on_a = a.reader();
+    let (locked, release, writer) = hold_writer(&b);
+    locked.recv().unwrap();
+
+    assert_eq!(b.try_reader_timeout(Duration::from_millis(50)).err(), Some(RwVecError::TimedOut));
+
+    release.send(()).unwrap();
+    writer.join().unwrap();
+    assert_eq!(&*b.reader(), &[4, 5, 6]);
+}
+
+//a second read on the same vec gets past a writer that is waiting on the first
+#[test]
+fn nested_read_on_same_vec_passes_waiting_writer() {
+    let vec = rwvec![1, 2, 3];
+    let first = vec.reader();
+
+    let writer = {
+        let vec = vec.clone();
+        std::thread::spawn(move || vec.writer()[0] = 10)
+    };
+    while !vec.rw_lock.writer.load(Ordering::SeqCst) {
+        std::thread::yield_now();
+    }
+
+    let second = vec.try_reader_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(&*second, &[1, 2, 3]);
+
+    drop(second);
+    drop(first);
+    writer.join().unwrap();
+    assert_eq!(&*vec.reader(), &[10, 2, 3]);
+}
+
+//reads held on more vecs than a thread has entries for still count as nested
+#[test]
+fn nested_read_past_the_held_read_entries() {
+    let others = (0..HELD_READS * 2).map(|i| rwvec![i]).collect::<std::vec::Vec<_>>();
+    let held   = others.iter().map(|other| other.reader()).collect::<std::vec::Vec<_>>();
+
+    let vec   = rwvec![1, 2, 3];
+    let first = vec.reader();
+
+    let writer = {
+        let vec = vec.clone();
+        std::thread::spawn(move || vec.writer()[0] = 10)
+    };
+    while !vec.rw_lock.writer.load(Ordering::SeqCst) {
+        std::thread::yield_now();
+    }
+
+    let second = vec.try_reader_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(&*second, &[1, 2, 3]);
+
+    drop(second);
+    drop(first);
+    drop(held);
+    writer.join().unwrap();
+    assert_eq!(&*vec.reader(), &[10, 2, 3]);
+}
+
+//a guard dropped on another thread gives its read back to the thread that took it, so
+//that thread doesn't go on walking past writers
+#[test]
+fn guard_dropped_elsewhere_releases_nesting() {
+    let vec = rwvec![1, 2, 3];
+
+    let guard = vec.reader();
+    std::thread::scope(|scope| {
+        scope.spawn(move || drop(guard));
+    });
+
+    let (locked, release, writer) = hold_writer(&vec);
+    locked.recv().unwrap();
+
+    assert_eq!(vec.try_reader_timeout(Duration::from_millis(50)).err(), Some(RwVecError::TimedOut));
+
+    release.send(()).unwrap();
+    writer.join().unwrap();
+}
+
+//preallocation can't lose updates made through &T while it grows the buffer
+#[test]
+fn preallocate_keeps_concurrent_atomic_updates() {
+    let vec = RWVec::<AtomicUsize>::builder().capacity(4).preallocate_at(0.5).build();
+    vec.push(AtomicUsize::new(0));
+
+    let adds = 50_000;
+    std::thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                for _ in 0..adds {
+                    vec.fetch_add_at(0, 1);
+                }
+            });
+        }
+
+        scope.spawn(|| {
+            for _ in 0..adds {
+                vec.push(AtomicUsize::new(0));
+            }
+        });
+    });
+
+    assert!(vec.stats().pushes > 4);
+    assert_eq!(vec.load_at(0), 2 * adds);
+}
+
+#[test]
+fn atomic_updates_survive_reallocating_pushes() {
+    let vec = RWVec::<std::sync::atomic::AtomicU64>::with_capacity(1);
+    vec.push(std::sync::atomic::AtomicU64::new(0));
+
+    let adds = 20_000;
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..adds {
+                    vec.fetch_add_at(0, 1);
+                }
+            });
+        }
+
+        scope.spawn(|| {
+            for i in 0..adds {
+                vec.push(std::sync::atomic::AtomicU64::new(i));
+            }
+        });
+    });
+
+    assert_eq!(vec.load_at(0), 4 * adds);
+    assert_eq!(vec.reader().len(), adds as usize + 1);
+}
+
+#[test]
+fn snapshot_together_lines_up_under_concurrent_pushes() {
+    let firsts  = RWVec::<usize>::new();
+    let seconds = RWVec::<usize>::new();
+    let done    = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        //always firsts then seconds, so seconds can never be ahead or more than one behind
+        scope.spawn(|| {
+            for i in 0..50_000 {
+                firsts.push(i);
+                seconds.push(i);
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+
+        while !done.load(Ordering::SeqCst) {
+            let (a, b) = snapshot_together((&*firsts, &*seconds));
+            assert!(b.len() <= a.len() && a.len() <= b.len() + 1, "{} / {}", a.len(), b.len());
+        }
+    });
+}
+
+#[test]
+fn events_arrive_in_push_order() {
+    let vec    = RWVec::<usize>::new();
+    let events = vec.subscribe();
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for i in 0..10_000 {
+                    vec.push(i);
+                }
+            });
+        }
+    });
+
+    let mut next = 0;
+    for event in events.try_iter() {
+        match event {
+            ChangeEvent::Appended { range } => {
+                assert_eq!(range.start, next);
+                next = range.end;
+            },
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+    assert_eq!(next, 40_000);
+}
+
+#[test]
+fn extract_if_keeps_both_sides_in_order() {
+    let vec = RWVec::<usize>::new();
+    for i in 0..100 {
+        vec.push(i);
+    }
+
+    let odd = vec.extract_if(|t| *t % 2 == 1);
+    assert_eq!(odd, (0..100).filter(|t| t % 2 == 1).collect::<std::vec::Vec<_>>());
+    assert_eq!(&*vec.reader(), &(0..100).filter(|t| t % 2 == 0).collect::<std::vec::Vec<_>>()[..]);
+}
+
+#[test]
+fn merge_moves_everything_in_order() {
+    let into = RWVec::<String>::with_capacity(1);
+    let from = RWVec::<String>::new();
+    into.push("a".to_string());
+    for i in 0..10 {
+        from.push(i.to_string());
+    }
+
+    into.merge(&from).unwrap();
+    assert_eq!(into.reader().len(), 11);
+    assert_eq!(into.reader()[10], "9");
+    assert_eq!(from.reader().len(), 0);
+}
+
+#[test]
+fn read_snapshot_keeps_its_len_across_pushes() {
+    let vec = RWVec::<usize>::with_capacity(16);
+    vec.push(0);
+
+    let mut snapshot = ReadSnapshot::new(&*vec);
+    vec.push(1);
+    assert_eq!(snapshot.len(), 1);
+
+    assert!(!snapshot.refresh());
+    assert_eq!(snapshot.len(), 2);
+
+    let (mut write, slipped) = snapshot.upgrade();
+    assert!(!slipped);
+    write[1] = 7;
+    drop(write);
+    assert_eq!(vec.reader()[1], 7);
+}
+
+#[test]
+fn snapshots_of_zero_sized_elements() {
+    let vec = RWVec::<()>::new();
+    vec.push(());
+    vec.push(());
+
+    let mut snapshot = ReadSnapshot::new(&*vec);
+    assert_eq!(snapshot.len(), 2);
+    vec.push(());
+    snapshot.refresh();
+    assert_eq!(snapshot.len(), 3);
+    drop(snapshot);
+
+    assert_eq!(WriteSnapshot::new(&*vec).len(), 3);
+}
+
+#[test]
+fn append_grows_once_and_empties_the_source() {
+    let vec = RWVec::<String>::with_capacity(1);
+    vec.push("0".to_string());
+
+    let mut other : std::vec::Vec<String> = (1..100).map(|i| i.to_string()).collect();
+    let capacity  = other.capacity();
+    vec.append(&mut other);
+
+    assert!(other.is_empty());
+    assert_eq!(other.capacity(), capacity);
+    assert_eq!(vec.stats().realloc_pushes, 1);
+
+    let reader = vec.reader();
+    assert_eq!(reader.len(), 100);
+    assert!(reader.iter().enumerate().all(|(i, t)| *t == i.to_string()));
+}
+
+#[test]
+fn columns_line_up_under_concurrent_pushes() {
+    struct Point { x : usize, y : usize }
+    rwvec_columns! { struct Points / PointsGuard for Point { x : usize, y : usize } }
+
+    let points = Points::new();
+    let done   = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 0..20_000 {
+                points.push(Point { x : i, y : 2 * i });
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+
+        while !done.load(Ordering::SeqCst) {
+            let reader = points.reader();
+            assert_eq!(reader.x().len(), reader.len());
+            assert_eq!(reader.y().len(), reader.len());
+            assert!(reader.x().iter().zip(reader.y()).all(|(x, y)| *y == 2 * x));
+        }
+    });
+
+    assert_eq!(points.len(), 20_000);
+}
+
+#[test]
+fn upgrade_lets_no_writer_in_between() {
+    let vec = RWVec::<u64>::new();
+    vec.push(0);
+
+    let rounds = 2_000;
+    std::thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                for _ in 0..rounds {
+                    //read, then write what was read plus one...a writer slipping in
+                    //between would lose its increment
+                    let reader = vec.upgradable();
+                    let seen   = reader[0];
+                    let mut writer = reader.upgrade();
+                    writer[0] = seen + 1;
+                }
+            });
+        }
+
+        for _ in 0..2 {
+            scope.spawn(|| {
+                for _ in 0..rounds {
+                    vec.writer()[0] += 1;
+                }
+            });
+        }
+
+        scope.spawn(|| {
+            for _ in 0..rounds {
+                let _ = vec.reader()[0];
+            }
+        });
+    });
+
+    assert_eq!(vec.reader()[0], 4 * rounds);
+}
+
+#[test]
+fn shrinks_once_below_the_watermark() {
+    let vec = RWVec::<u32>::builder().capacity(64).shrink_when_below(0.25, Duration::from_millis(0)).build();
+    for i in 0..3 {
+        vec.push(i);
+    }
+
+    vec.pop();
+    assert!(unsafe { vec.vec().capacity() } < 64);
+    assert_eq!(&*vec.reader(), &[0, 1][..]);
+}
+
+#[test]
+fn zero_sized_pushes_only_count() {
+    let vec = RWVec::<()>::new();
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..1_000 {
+                    vec.push(());
+                }
+            });
+        }
+    });
+
+    assert_eq!(vec.reader().len(), 4_000);
+    assert_eq!(vec.pop(), Some(()));
+    assert_eq!(vec.reader().len(), 3_999);
+    assert_eq!(unsafe { vec.vec().capacity() }, usize::MAX);
+}
+
+#[test]
+fn reader_fence_waits_for_earlier_readers() {
+    let vec = RWVec::<u32>::new();
+    vec.push(1);
+
+    let reader    = vec.reader();
+    let mut fence = vec.fence_readers();
+    assert!(!fence.is_done());
+
+    //readers from after the fence don't hold it up
+    let later = vec.reader();
+    drop(reader);
+    assert!(fence.is_done());
+    drop(later);
+}
+
+#[test]
+fn cached_reader_follows_the_version() {
+    let vec = RWVec::<u32>::new();
+    vec.push(1);
+
+    let mut cached = CachedReader::new(vec.clone());
+    let version    = cached.get().version();
+    assert_eq!(cached.get().version(), version);
+    assert_eq!(&cached.get().data[..], &[1][..]);
+
+    vec.writer()[0] = 2;
+    assert!(cached.get().version() != version);
+    assert_eq!(&cached.get().data[..], &[2][..]);
+}
+
+#[test]
+fn swap_vec_readers_keep_their_version() {
+    let vec = RWSwapVec::from_vec(vec![1, 2]);
+    let old = vec.reader();
+
+    std::thread::scope(|scope| {
+        for i in 0..4 {
+            let vec = &vec;
+            scope.spawn(move || {
+                for j in 0..100 {
+                    vec.push(i * 100 + j);
+                    assert!(vec.reader().len() >= 3);
+                }
+            });
+        }
+    });
+
+    assert_eq!(&old[..], &[1, 2][..]);
+    assert_eq!(vec.reader().len(), 402);
+}
+
+#[cfg(feature = "watchdog")]
+#[test]
+fn watchdog_reports_long_held_guards() {
+    let (sender, reports) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let vec    = RWVec::<u32>::builder().name("held").watchdog(Duration::from_millis(10), move |held : &HeldGuard| {
+        let _ = sender.lock().unwrap().send((held.vec.clone(), held.kind));
+    }).build();
+
+    let reader = vec.reader();
+    let report = reports.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(report, (Some("held".to_string()), "read"));
+    drop(reader);
+}
+
+#[test]
+fn name_shows_up_in_panics() {
+    let vec = RWVec::<u32>::builder().name("ids").build();
+    assert_eq!(vec.name(), Some("ids"));
+
+    let panic   = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vec.replace(3, 0))).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("RWVec 'ids': index 3 out of bounds"), "{}", message);
+}
+
+#[test]
+fn get_mut_when_unique() {
+    let mut vec = RWVec::<u32>::new();
+    vec.push(1);
+
+    Arc::get_mut(&mut vec).unwrap().get_mut().extend(vec![2, 3]);
+    assert_eq!(&*vec.reader(), &[1, 2, 3][..]);
+
+    let other = vec.clone();
+    assert!(Arc::get_mut(&mut vec).is_none());
+    drop(other);
+}
+
+#[test]
+fn take_all_swaps_the_contents_out() {
+    let vec = RWVec::<String>::new();
+    vec.push("a".to_string());
+    vec.push("b".to_string());
+
+    assert_eq!(vec.take_all(), vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(vec.reader().len(), 0);
+
+    vec.push("c".to_string());
+    assert_eq!(vec.take_all_reserving(8), vec!["c".to_string()]);
+    assert!(unsafe { vec.vec().capacity() } >= 8);
+}
+
+#[test]
+fn cloned_guard_outlives_the_original() {
+    let vec = RWVec::<u32>::with_capacity(8);
+    vec.push(1);
+
+    let reader = vec.reader();
+    let clone  = reader.clone();
+    drop(reader);
+    vec.push(2);
+
+    assert_eq!(&*clone, &[1][..]);
+    drop(clone);
+    assert_eq!(&*vec.writer(), &[1, 2][..]);
+}
+
+#[test]
+fn take_leaves_the_default() {
+    let vec = RWVec::<String>::new();
+    vec.push("a".to_string());
+    vec.push("b".to_string());
+
+    assert_eq!(vec.take(1), "b");
+    assert_eq!(&*vec.reader(), &["a".to_string(), String::new()][..]);
+}
+
+#[test]
+fn replace_hands_back_every_old_value_once() {
+    let vec = RWVec::<usize>::new();
+    vec.push(0);
+
+    //every value put in comes back out exactly once, so no two replaces saw the same slot
+    let taken = Mutex::new(std::vec::Vec::new());
+    std::thread::scope(|scope| {
+        for i in 0..4 {
+            let (vec, taken) = (&vec, &taken);
+            scope.spawn(move || {
+                for j in 1..=1_000 {
+                    let old = vec.replace(0, i * 1_000 + j);
+                    taken.lock().unwrap().push(old);
+                }
+            });
+        }
+    });
+
+    let mut taken = taken.into_inner().unwrap();
+    taken.push(vec.reader()[0]);
+    taken.sort();
+    assert_eq!(taken, (0..=4_000).collect::<std::vec::Vec<_>>());
+}
+
+#[test]
+fn extend_from_slice_lands_in_one_piece() {
+    let vec = RWVec::<u32>::with_capacity(1);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for _ in 0..1_000 {
+                vec.push(0);
+            }
+        });
+
+        for _ in 0..100 {
+            vec.extend_from_slice(&[1, 2, 3, 4]);
+        }
+    });
+
+    //every batch went in under one hold of the push lock, so none got split up
+    let reader  = vec.reader();
+    let batches = reader.iter().enumerate().filter(|&(_, t)| *t == 1).map(|(i, _)| i).collect::<std::vec::Vec<_>>();
+    assert_eq!(batches.len(), 100);
+    assert!(batches.iter().all(|&i| reader[i..i + 4] == [1, 2, 3, 4]));
+    assert_eq!(reader.len(), 1_400);
+}
+
+#[test]
+fn push_indexed_matches_where_it_landed() {
+    let vec = RWVec::<usize>::new();
+
+    std::thread::scope(|scope| {
+        for i in 0..4 {
+            let vec = &vec;
+            scope.spawn(move || {
+                for j in 0..1_000 {
+                    let value = i * 1_000 + j;
+                    let index = vec.push_indexed(value);
+                    assert_eq!(vec.reader()[index], value);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn push_get_sees_its_own_element() {
+    let vec = RWVec::<usize>::with_capacity(1);
+
+    std::thread::scope(|scope| {
+        for i in 0..4 {
+            let vec = &vec;
+            scope.spawn(move || {
+                for j in 0..1_000 {
+                    let guard = vec.push_get(i * 1_000 + j);
+                    assert_eq!(*guard, i * 1_000 + j);
+                    assert_eq!(vec.reader()[guard.index()], *guard);
+                }
+            });
+        }
+    });
+
+    assert_eq!(vec.reader().len(), 4_000);
+}
+
+#[test]
+fn push_with_builds_from_the_index() {
+    let vec = RWVec::<usize>::new();
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..1_000 {
+                    vec.push_with(|index| index * 2);
+                }
+            });
+        }
+    });
+
+    assert!(vec.reader().iter().enumerate().all(|(i, t)| *t == i * 2));
+    assert_eq!(vec.try_push_with(|index| index), Ok(4_000));
+}
+
+#[test]
+fn pop_wait_wakes_for_a_push() {
+    let vec = RWVec::<u32>::new();
+    assert_eq!(vec.pop_timeout(Duration::from_millis(10)), None);
+
+    std::thread::scope(|scope| {
+        let popper = scope.spawn(|| vec.pop_wait());
+        std::thread::sleep(Duration::from_millis(10));
+        vec.push(7);
+        assert_eq!(popper.join().unwrap(), 7);
+    });
+
+    assert_eq!(vec.pop(), None);
+}
+
+#[test]
+fn queue_delivers_each_element_once() {
+    let vec                = RWVec::<usize>::new();
+    let (sender, receiver) = RWVec::as_queue(&vec);
+
+    let mut received = std::thread::scope(|scope| {
+        let receivers = (0..3).map(|_| {
+            let receiver = receiver.clone();
+            scope.spawn(move || {
+                let mut got = std::vec::Vec::new();
+                while let Some(t) = receiver.recv() {
+                    got.push(t);
+                }
+                got
+            })
+        }).collect::<std::vec::Vec<_>>();
+
+        for i in 0..3_000 {
+            sender.send(i);
+        }
+        drop(sender);
+        drop(receiver);
+
+        receivers.into_iter().flat_map(|receiver| receiver.join().unwrap()).collect::<std::vec::Vec<_>>()
+    });
+
+    received.sort();
+    assert_eq!(received, (0..3_000).collect::<std::vec::Vec<_>>());
+}
+
+#[test]
+fn steal_batch_takes_the_tail_in_order() {
+    let vec = RWVec::<u32>::new();
+    for i in 0..10 {
+        vec.push(i);
+    }
+
+    assert_eq!(vec.steal_batch(3), vec![7, 8, 9]);
+    assert!(vec.steal_batch(0).is_empty());
+    assert_eq!(vec.steal_batch(100), (0..7).collect::<std::vec::Vec<_>>());
+    assert!(vec.steal_batch(1).is_empty());
+}
+
+#[test]
+fn parallel_passes_visit_everything_once() {
+    let vec = RWVec::<usize>::new();
+    for i in 0..1_001 {
+        vec.push(i);
+    }
+
+    vec.for_each_parallel_mut(4, |t| *t += 1);
+
+    let sum = AtomicUsize::new(0);
+    vec.for_each_parallel(3, |t| { sum.fetch_add(*t, Ordering::SeqCst); });
+    assert_eq!(sum.into_inner(), (1..1_002).sum::<usize>());
+
+    RWVec::<usize>::new().for_each_parallel(4, |_| panic!("nothing to visit"));
+}
+
+#[test]
+fn bitvec_sets_bits_from_many_threads() {
+    let bits = RWBitVec::new();
+    bits.grow(1_000);
+
+    std::thread::scope(|scope| {
+        for i in 0..4 {
+            let bits = &bits;
+            scope.spawn(move || {
+                for index in (i..1_000).step_by(4) {
+                    if index % 3 == 0 {
+                        assert!(!bits.set(index));
+                    }
+                }
+            });
+        }
+
+        scope.spawn(|| {
+            for _ in 0..1_000 {
+                bits.push(true);
+            }
+        });
+    });
+
+    assert_eq!(bits.len(), 2_000);
+    let expected = (0..1_000).filter(|i| i % 3 == 0).chain(1_000..2_000).collect::<std::vec::Vec<_>>();
+    assert_eq!(bits.ones(), expected);
+    assert!(bits.clear(3));
+    assert!(!bits.test(3));
+}
+
+#[test]
+fn byte_writes_are_not_interleaved() {
+    use std::io::Write;
+
+    let vec = RWVec::<u8>::new();
+    std::thread::scope(|scope| {
+        for byte in 0..4u8 {
+            let vec = &vec;
+            scope.spawn(move || {
+                let mut writer = vec.byte_writer();
+                for _ in 0..100 {
+                    writer.write_all(&[byte; 16]).unwrap();
+                }
+            });
+        }
+    });
+
+    let bytes = vec.reader();
+    assert_eq!(bytes.len(), 4 * 100 * 16);
+    assert!(bytes.chunks(16).all(|chunk| chunk.iter().all(|&b| b == chunk[0])));
+}
+
+#[test]
+fn byte_cursor_reads_and_seeks() {
+    use std::io::{ Read, Seek, SeekFrom };
+
+    let vec = RWVec::<u8>::new();
+    vec.extend_from_slice(b"hello ");
+
+    let mut cursor = vec.byte_cursor();
+    let mut buf    = [0; 3];
+    assert_eq!(cursor.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf, b"hel");
+
+    vec.extend_from_slice(b"world");
+    let mut rest = String::new();
+    cursor.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "lo world");
+
+    assert_eq!(cursor.seek(SeekFrom::End(-5)).unwrap(), 6);
+    assert!(cursor.seek(SeekFrom::Current(-7)).is_err());
+
+    //a blocking cursor waits for bytes that aren't there yet
+    let mut blocking = vec.byte_cursor_at(11).blocking();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(Duration::from_millis(10));
+            vec.extend_from_slice(b"!");
+        });
+        assert_eq!(blocking.read(&mut buf).unwrap(), 1);
+    });
+    assert_eq!(buf[0], b'!');
+}
+
+#[test]
+fn byte_cursor_reads_lines() {
+    use std::io::BufRead;
+
+    let vec = RWVec::<u8>::new();
+    vec.extend_from_slice(b"one\ntwo\nthree");
+
+    let lines = vec.byte_cursor().lines().map(|line| line.unwrap()).collect::<std::vec::Vec<_>>();
+    assert_eq!(lines, vec!["one", "two", "three"]);
+}
+
+#[test]
+fn bounded_vecs_follow_their_policy() {
+    let rejecting = RWVec::<u32>::builder().bounded(2, OverflowPolicy::Reject).build();
+    rejecting.push(1);
+    rejecting.push(2);
+    assert_eq!(rejecting.try_push(3), Err(3));
+
+    let oldest = RWVec::<u32>::builder().bounded(2, OverflowPolicy::DropOldest).build();
+    for i in 0..5 {
+        oldest.push(i);
+    }
+    assert_eq!(&*oldest.reader(), &[3, 4][..]);
+
+    let newest = RWVec::<u32>::builder().bounded(2, OverflowPolicy::DropNewest).build();
+    for i in 0..5 {
+        newest.push(i);
+    }
+    assert_eq!(&*newest.reader(), &[0, 1][..]);
+
+    let blocking = RWVec::<u32>::builder().bounded(2, OverflowPolicy::Block).build();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 0..100 {
+                blocking.push(i);
+            }
+        });
+
+        let mut popped = std::vec::Vec::new();
+        while popped.len() < 100 {
+            assert!(blocking.reader().len() <= 2);
+            popped.extend(blocking.steal_batch(2));
+        }
+        assert_eq!(popped.len(), 100);
+    });
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn permits_hold_their_slot() {
+    let vec    = RWVec::<u32>::builder().bounded(1, OverflowPolicy::Reject).build();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let permit = match vec.poll_reserve(&mut cx) {
+        Poll::Ready(permit) => permit,
+        Poll::Pending       => panic!("an empty vec has room")
+    };
+
+    //the slot is spoken for, so neither pushes nor other permits get it
+    assert_eq!(vec.try_push(1), Err(1));
+    assert!(vec.poll_reserve(&mut cx).is_pending());
+
+    assert_eq!(permit.push(2), 0);
+    assert!(vec.poll_reserve(&mut cx).is_pending());
+    vec.pop();
+    assert!(vec.poll_reserve(&mut cx).is_ready());
+}
+
+#[test]
+fn watermarks_fire_once_per_crossing() {
+    let (sender, crossings) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let vec    = RWVec::<u32>::builder().bounded(10, OverflowPolicy::Reject).watermarks(0.2, 0.8, move |mark| {
+        sender.lock().unwrap().send(mark).unwrap();
+    }).build();
+
+    for i in 0..10 {
+        vec.push(i);
+    }
+    vec.steal_batch(9);
+    vec.push(0);
+
+    assert_eq!(crossings.try_iter().collect::<std::vec::Vec<_>>(), vec![Watermark::High, Watermark::Low]);
+}
+
+#[test]
+fn drains_shrink_to_the_floor() {
+    let vec = RWVec::<u32>::builder().shrink_on_drain(4).build();
+    for i in 0..100 {
+        vec.push(i);
+    }
+
+    assert_eq!(vec.drain(10..), (10..100).collect::<std::vec::Vec<_>>());
+    assert!(unsafe { vec.vec().capacity() } < 100);
+
+    vec.clear();
+    assert_eq!(vec.reader().len(), 0);
+    assert!(unsafe { vec.vec().capacity() } <= 8);
+}
+
+#[cfg(feature = "debug-locks")]
+#[test]
+fn dump_shows_the_writer() {
+    let vec = RWVec::<u32>::new();
+    drop(vec.reader());
+
+    let writer = vec.writer();
+    let dump   = vec.dump();
+    assert_eq!(dump.writer.map(|holder| holder.kind), Some("write"));
+    assert!(dump.recent_readers.iter().any(|holder| holder.kind == "read"));
+    drop(writer);
+
+    assert!(vec.dump().writer.is_none());
+}
+
+#[test]
+fn panicking_writer_poisons() {
+    let vec = RWVec::<u32>::new();
+    vec.push(1);
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut writer = vec.writer();
+        writer[0] = 2;
+        panic!("half way through");
+    }));
+    assert!(panicked.is_err());
+
+    assert!(vec.is_poisoned());
+    assert_eq!(vec.try_reader().err(), Some(RwVecError::Poisoned));
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vec.reader().len())).is_err());
+    assert_eq!(vec.reader_ignoring_poison()[0], 2);
+
+    vec.clear_poison();
+    assert_eq!(vec.reader()[0], 2);
+}
+
+#[test]
+fn try_guards_fail_instead_of_waiting() {
+    let vec = RWVec::<u32>::new();
+
+    let writer = vec.writer();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            assert_eq!(vec.try_reader().err(), Some(RwVecError::WouldBlock));
+            assert_eq!(vec.try_reader_timeout(Duration::from_millis(5)).err(), Some(RwVecError::TimedOut));
+            assert_eq!(vec.try_writer().err(), Some(RwVecError::WouldBlock));
+        });
+    });
+    drop(writer);
+
+    let reader = vec.reader();
+    std::thread::scope(|scope| {
+        scope.spawn(|| assert_eq!(vec.try_writer_timeout(Duration::from_millis(5)).err(), Some(RwVecError::TimedOut)));
+    });
+    drop(reader);
+
+    assert!(vec.try_writer().is_ok());
+}
+
+#[test]
+fn try_reserve_grows_once() {
+    let vec = RWVec::<u64>::new();
+    vec.push(1);
+
+    assert!(vec.try_reserve(100).is_ok());
+    let capacity = unsafe { vec.vec().capacity() };
+    assert!(capacity >= 101);
+
+    for i in 0..100 {
+        vec.push(i);
+    }
+    assert_eq!(unsafe { vec.vec().capacity() }, capacity);
+
+    assert!(vec.try_reserve(usize::MAX).is_err());
+    assert_eq!(vec.reader().len(), 101);
+}
+
+#[test]
+fn cloned_chunks_cover_everything() {
+    let vec = RWVec::<u32>::new();
+    for i in 0..10 {
+        vec.push(i);
+    }
+
+    let mut chunks = vec.iter_cloned_chunks(4);
+    assert_eq!(chunks.next(), Some(vec![0, 1, 2, 3]));
+
+    //nothing is locked between chunks
+    vec.push(10);
+    assert_eq!(chunks.collect::<std::vec::Vec<_>>(), vec![vec![4, 5, 6, 7], vec![8, 9, 10]]);
+}
+
+#[test]
+fn stats_count_pushes_reallocs_and_upgrades() {
+    let vec = RWVec::<u32>::with_capacity(1);
+    vec.push(1);
+    vec.push(2);
+    vec.extend_from_slice(&[3, 4, 5]);
+
+    let mut writer = vec.writer();
+    writer.refresh();
+    drop(writer.upgrade());
+    drop(writer);
+
+    let stats = vec.stats();
+    assert_eq!(stats.pushes, 5);
+    assert_eq!(stats.realloc_pushes, 2);
+    assert_eq!(stats.refreshes, 1);
+    assert_eq!(stats.upgrades, 1);
+}
+
+#[cfg(feature = "debug-events")]
+#[test]
+fn event_log_records_structural_changes() {
+    let vec = RWVec::<u32>::with_capacity(1);
+    vec.push(1);
+    vec.push(2);
+
+    //std and the wiping grow under zeroize don't agree on how far to grow
+    let grown = unsafe { vec.vec().capacity() };
+    vec.pop();
+
+    let events = vec.events().into_iter().map(|logged| logged.event).collect::<std::vec::Vec<_>>();
+    assert_eq!(events, vec![
+        Event::Pushed    { start : 0, end : 1 },
+        Event::Realloc   { from : 1, to : grown },
+        Event::Pushed    { start : 1, end : 2 },
+        Event::Exclusive { before : 2, after : 1 }
+    ]);
+}
+
+#[test]
+fn split_handles_push_and_read_the_same_vec() {
+    let (producer, readers) = RWVec::split(RWVec::<u32>::new());
+
+    let pusher = producer.clone();
+    std::thread::spawn(move || {
+        for i in 0..100 {
+            pusher.push(i);
+        }
+    }).join().unwrap();
+
+    assert_eq!(producer.push_indexed(100), 100);
+    assert_eq!(readers.clone().reader().len(), 101);
+    assert_eq!(readers.snapshot().version(), readers.version());
+}
+
+#[cfg(test)]
+fn hammer(vec : &RWVec<usize>) {
+    std::thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                for i in 0..2_000 {
+                    vec.push(i);
+                }
+            });
+        }
+
+        scope.spawn(|| {
+            for _ in 0..500 {
+                let reader = vec.reader();
+                assert!(reader.iter().all(|t| *t < 2_000 || *t >= 1_000_000));
+            }
+        });
+
+        scope.spawn(|| {
+            for _ in 0..500 {
+                if let Some(t) = vec.writer().first_mut() {
+                    *t = 1_000_000;
+                }
+            }
+        });
+    });
+
+    assert_eq!(vec.reader().len(), 4_000);
+}
+
+#[test]
+fn spin_backend_under_contention() {
+    hammer(&RWVec::builder().capacity(1).lock_backend(LockBackend::Spin).build());
+}
+
+#[test]
+fn adaptive_backend_under_contention() {
+    hammer(&RWVec::builder().capacity(1).lock_backend(LockBackend::adaptive()).build());
+}
+
+#[test]
+fn array_vec_fills_up_exactly() {
+    static FILLED : RWArrayVec<usize, 1_000> = RWArrayVec::new();
+
+    let rejected = std::thread::scope(|scope| {
+        let pushers = (0..4).map(|i| scope.spawn(move || {
+            (0..300).filter(|j| FILLED.push(i * 300 + j).is_err()).count()
+        })).collect::<std::vec::Vec<_>>();
+
+        pushers.into_iter().map(|pusher| pusher.join().unwrap()).sum::<usize>()
+    });
+
+    assert_eq!(rejected, 200);
+    assert_eq!(FILLED.len(), FILLED.capacity());
+
+    let mut seen = FILLED.reader().to_vec();
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 1_000);
+}
+
+#[test]
+fn snapshot_diff_reports_what_changed() {
+    let vec = RWVec::<u32>::new();
+    vec.extend_from_slice(&[1, 2, 3]);
+    let before = vec.snapshot();
+
+    vec.writer()[1] = 20;
+    vec.push(4);
+    let diff = before.diff(&vec.reader());
+    assert_eq!(diff.modified, vec![1]);
+    assert_eq!(diff.appended, 3..4);
+    assert_eq!(diff.truncated, None);
+
+    vec.drain(1..);
+    let diff = before.diff(&vec.reader());
+    assert!(diff.modified.is_empty());
+    assert_eq!(diff.truncated, Some(1));
+    assert!(before.diff(&before.data).is_empty());
+}
+
+#[test]
+fn broadcast_reaches_every_receiver() {
+    let vec     = RWVec::<u32>::new();
+    let keeping = vec.broadcast(100);
+    let lagging = vec.broadcast(2);
+
+    for i in 0..5 {
+        vec.push(i);
+    }
+
+    assert_eq!((0..5).map(|_| keeping.recv().unwrap()).collect::<std::vec::Vec<_>>(), vec![0, 1, 2, 3, 4]);
+    assert_eq!(lagging.recv(), Err(BroadcastError::Lagged(3)));
+    assert_eq!(lagging.recv(), Ok(3));
+    assert_eq!(lagging.try_recv(), Some(Ok(4)));
+    assert_eq!(lagging.try_recv(), None);
+
+    drop(vec);
+    assert_eq!(keeping.recv(), Err(BroadcastError::Closed));
+}
+
+#[test]
+fn watch_last_sees_the_newest() {
+    let vec       = RWVec::<u32>::new();
+    let mut watch = RWVec::watch_last(&vec);
+    assert!(watch.borrow().is_none());
+    assert!(!watch.changed_timeout(Duration::from_millis(5)));
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(Duration::from_millis(10));
+            vec.push(1);
+            vec.push(2);
+        });
+        watch.changed();
+    });
+
+    assert_eq!(*watch.borrow().unwrap(), 2);
+    assert!(!watch.has_changed());
+}
+
+#[test]
+fn expire_drops_the_old_front() {
+    let vec = RWVec::<Timed<u32>>::new();
+    vec.push_timed(1);
+    vec.push_timed(2);
+    std::thread::sleep(Duration::from_millis(30));
+    vec.push_timed(3);
+
+    assert_eq!(vec.expire(Duration::from_millis(20)), 2);
+    assert_eq!(vec.expire(Duration::from_millis(20)), 0);
+    assert_eq!(*vec.reader()[0], 3);
+}
+
+#[test]
+fn byte_budget_evicts_the_oldest() {
+    let vec = RWVec::<String>::builder().byte_budget(10, |s| s.len()).build();
+    for s in ["aaaa", "bbbb", "cccc", "dd"] {
+        vec.push(s.to_string());
+    }
+
+    //the push that went over is kept, the oldest go before the next one
+    assert_eq!(&*vec.reader(), &["bbbb", "cccc", "dd"].map(String::from)[..]);
+    vec.push("eeee".to_string());
+    vec.push("f".to_string());
+    assert_eq!(&*vec.reader(), &["cccc", "dd", "eeee", "f"].map(String::from)[..]);
+}
+
+#[test]
+fn new_mut_is_what_the_refresh_brought() {
+    let vec = RWVec::<u32>::with_capacity(8);
+    vec.push(1);
+
+    let mut writer = vec.writer();
+    assert!(writer.new_mut().is_empty());
+
+    vec.push(2);
+    vec.push(3);
+    writer.refresh();
+    for t in writer.iter_new_mut() {
+        *t *= 10;
+    }
+    drop(writer);
+
+    assert_eq!(&*vec.reader(), &[1, 20, 30][..]);
+}
+
+#[test]
+fn leaked_vec_is_static() {
+    let vec : &'static RWVec<u32> = RWVec::leak(RWVec::new());
+
+    std::thread::spawn(move || vec.push(1)).join().unwrap();
+    let reader : SliceGuard<'static, u32> = vec.reader();
+    assert_eq!(&*reader, &[1][..]);
+}
+
+#[test]
+fn byte_cursor_resumes_from_its_position() {
+    use std::io::Read;
+
+    let vec = RWVec::<u8>::new();
+    vec.extend_from_slice(b"first second");
+
+    let mut cursor = vec.byte_cursor();
+    let mut word   = [0; 6];
+    cursor.read_exact(&mut word).unwrap();
+    let saved = cursor.position();
+    drop(cursor);
+
+    let mut rest = String::new();
+    vec.byte_cursor_at(saved).read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "second");
+}
+
+#[test]
+fn rwvec_macro_forms() {
+    let empty : Arc<RWVec<u32>> = rwvec![];
+    assert_eq!(empty.reader().len(), 0);
+    assert_eq!(&*rwvec![7; 3].reader(), &[7, 7, 7][..]);
+    assert_eq!(&*rwvec![1, 2, 3,].reader(), &[1, 2, 3][..]);
+}
+
+#[cfg(all(feature = "json", feature = "csv"))]
+#[test]
+fn tail_writes_everything_appended_after_it_started() {
+    let vec = RWVec::<(u32, String)>::new();
+    vec.push((0, "before".to_string()));
+
+    let json = RWVec::tail(&vec, std::vec::Vec::new(), TailFormat::JsonLines);
+    let csv  = RWVec::tail(&vec, std::vec::Vec::new(), TailFormat::Csv);
+    vec.push((1, "a".to_string()));
+    vec.push((2, "b".to_string()));
+
+    assert_eq!(String::from_utf8(json.stop().unwrap()).unwrap(), "[1,\"a\"]\n[2,\"b\"]\n");
+    assert_eq!(String::from_utf8(csv.stop().unwrap()).unwrap(), "1,a\n2,b\n");
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn rayon_extend_and_collect() {
+    use rayon::iter::{ IntoParallelIterator, ParallelIterator };
+
+    let vec = RWVec::<u32>::new();
+    vec.par_extend((0..10_000).into_par_iter());
+
+    let mut all = vec.reader().to_vec();
+    all.sort();
+    assert_eq!(all, (0..10_000).collect::<std::vec::Vec<_>>());
+
+    let collected : RWVec<u32> = (0..100).into_par_iter().map(|i| i * 2).collect();
+    assert_eq!(collected.sum(), 9_900);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_recv_without_a_runtime() {
+    let vec                = RWVec::<u32>::new();
+    let (sender, receiver) = RWVec::as_queue(&vec);
+    let mut cx             = Context::from_waker(Waker::noop());
+
+    assert_eq!(receiver.poll_recv(&mut cx), Poll::Pending);
+    sender.send(1);
+    assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(Some(1)));
+
+    drop(sender);
+    assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(None));
+}
+
+#[cfg(all(feature = "async", feature = "futures-core"))]
+#[test]
+fn receiver_is_a_stream() {
+    use futures_core::Stream;
+
+    let vec                    = RWVec::<u32>::new();
+    let (sender, mut receiver) = RWVec::as_queue(&vec);
+    let mut cx                 = Context::from_waker(Waker::noop());
+
+    sender.send(1);
+    drop(sender);
+    assert_eq!(Pin::new(&mut receiver).poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert_eq!(Pin::new(&mut receiver).poll_next(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+fn guards_project_onto_fields() {
+    let vec = RWVec::<(u32, String)>::with_capacity(1);
+    vec.push((1, "one".to_string()));
+    vec.push((2, "two".to_string()));
+
+    let name = vec.reader().map_element(1, |t| &t.1[..]);
+    assert_eq!(&*name, "two");
+    drop(name);
+
+    let pushed = vec.push_get((3, "three".to_string())).map(|t| &t.0);
+    assert_eq!(*pushed, 3);
+
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vec.reader().map_element(3, |t| &t.0))).is_err());
+}
+
+#[test]
+fn partition_empties_into_both_sides() {
+    let vec = RWVec::<u32>::new();
+    for i in 0..10 {
+        vec.push(i);
+    }
+
+    let (small, big) = vec.partition(|t| *t < 4);
+    assert_eq!(small, vec![0, 1, 2, 3]);
+    assert_eq!(big, (4..10).collect::<std::vec::Vec<_>>());
+    assert_eq!(vec.reader().len(), 0);
+}
+
+#[test]
+fn dedup_drops_repeats_in_a_row() {
+    let vec = RWVec::<u32>::new();
+    vec.extend_from_slice(&[1, 1, 2, 2, 2, 1, 3, 3]);
+    vec.dedup();
+    assert_eq!(&*vec.reader(), &[1, 2, 1, 3][..]);
+
+    vec.dedup_by_key(|t| *t % 2);
+    assert_eq!(&*vec.reader(), &[1, 2, 1][..]);
+}
+
+#[test]
+fn rotate_only_moves_what_was_there() {
+    let vec = RWVec::<u32>::new();
+    vec.extend_from_slice(&[1, 2, 3, 4]);
+
+    vec.rotate_left(1);
+    assert_eq!(&*vec.reader(), &[2, 3, 4, 1][..]);
+    vec.rotate_right(2);
+    assert_eq!(&*vec.reader(), &[4, 1, 2, 3][..]);
+
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vec.rotate_left(5))).is_err());
+}
+
+#[test]
+fn apply_in_place_lets_readers_in_between() {
+    let vec = RWVec::<u32>::new();
+    for _ in 0..1_000 {
+        vec.push(0);
+    }
+
+    //every reader sees a prefix done and the rest untouched, never anything in between
+    std::thread::scope(|scope| {
+        scope.spawn(|| vec.apply_in_place(10, |t| *t += 1));
+
+        for _ in 0..100 {
+            let reader = vec.reader();
+            let done   = reader.iter().take_while(|t| **t == 1).count();
+            assert!(done % 10 == 0 && reader[done..].iter().all(|t| *t == 0));
+        }
+    });
+
+    assert!(vec.reader().iter().all(|t| *t == 1));
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn shuffle_keeps_every_element() {
+    use rand::SeedableRng;
+
+    let vec = RWVec::<u32>::new();
+    for i in 0..100 {
+        vec.push(i);
+    }
+
+    vec.shuffle(&mut rand::rngs::StdRng::seed_from_u64(7));
+    let mut shuffled = vec.reader().to_vec();
+    assert!(shuffled != (0..100).collect::<std::vec::Vec<_>>());
+    shuffled.sort();
+    assert_eq!(shuffled, (0..100).collect::<std::vec::Vec<_>>());
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn sample_picks_distinct_elements() {
+    use rand::SeedableRng;
+
+    let vec = RWVec::<u32>::new();
+    for i in 0..100 {
+        vec.push(i);
+    }
+
+    let mut rng    = rand::rngs::StdRng::seed_from_u64(7);
+    let mut picked = vec.sample(&mut rng, 10);
+    picked.sort();
+    picked.dedup();
+    assert_eq!(picked.len(), 10);
+    assert!(picked.iter().all(|t| *t < 100));
+    assert_eq!(vec.sample(&mut rng, 1_000).len(), 100);
+}
+
+#[test]
+fn order_statistics() {
+    let vec = RWVec::<u32>::new();
+    vec.extend_from_slice(&[5, 1, 9, 3, 7]);
+
+    assert_eq!(vec.nth_smallest(1), Some(3));
+    assert_eq!(vec.nth_smallest(5), None);
+    assert_eq!(vec.top_k(2), vec![9, 7]);
+    assert_eq!(vec.top_k(10), vec![9, 7, 5, 3, 1]);
+
+    vec.select_nth_unstable(2);
+    let reader = vec.reader();
+    assert_eq!(reader[2], 5);
+    assert!(reader[..2].iter().all(|t| *t < 5) && reader[3..].iter().all(|t| *t > 5));
+}
+
+#[test]
+fn numeric_aggregates() {
+    let ints = RWVec::<i32>::new();
+    assert_eq!((ints.sum(), ints.min(), ints.max(), ints.mean()), (0, None, None, None));
+    ints.extend_from_slice(&[3, -1, 4]);
+    assert_eq!((ints.sum(), ints.min(), ints.max(), ints.mean()), (6, Some(-1), Some(4), Some(2.0)));
+
+    //NaNs don't win either way
+    let floats = RWVec::<f64>::new();
+    floats.extend_from_slice(&[f64::NAN, 2.0, 0.5]);
+    assert_eq!((floats.min(), floats.max()), (Some(0.5), Some(2.0)));
+}
+
+#[test]
+fn scans_agree_with_a_plain_pass() {
+    for len in 0..100 {
+        let elements = (0..len).map(|i| (i * 37 % 101) as u32).collect::<std::vec::Vec<_>>();
+        let vec      = RWVec::from_vec(elements.clone());
+
+        assert_eq!(vec.sum(), elements.iter().sum::<u32>());
+        assert_eq!(vec.min(), elements.iter().cloned().min());
+        assert_eq!(vec.max(), elements.iter().cloned().max());
+
+        let reader = vec.reader();
+        for value in [0, 36, 100] {
+            assert_eq!(u32::position_of(&reader, value), elements.iter().position(|t| *t == value));
+        }
+    }
+}
+
+#[cfg(feature = "huge-pages")]
+#[test]
+fn huge_pages_across_reallocs() {
+    let vec = RWVec::<u64>::builder().huge_pages().build();
+    for i in 0..(1 << 20) {
+        vec.push(i);
+    }
+
+    vec.drain(1_000..);
+    assert_eq!(vec.sum(), (0..1_000).sum::<u64>());
+}
+
+#[test]
+fn wiping_paths_keep_the_elements() {
+    let mut vec = vec!["a".to_string(), "b".to_string()];
+    vec.shrink_to_fit();
+
+    reserve_wiping(&mut vec, 1).unwrap();
+    assert!(vec.capacity() >= 3);
+    assert_eq!(vec, ["a", "b"]);
+
+    shrink_wiping(&mut vec, 0);
+    assert_eq!(vec.capacity(), 2);
+    assert_eq!(vec, ["a", "b"]);
+
+    vec.clear();
+    free_wiping(vec);
+}
+
+#[cfg(all(feature = "serde", feature = "zstd"))]
+#[test]
+fn compressed_round_trip() {
+    let vec = RWVec::<(u32, String)>::new();
+    for i in 0..1_000 {
+        vec.push((i, i.to_string()));
+    }
+
+    let exported = vec.export_compressed(std::vec::Vec::new(), 0).unwrap();
+    let imported = RWVec::<(u32, String)>::import_compressed(&exported[..]).unwrap();
+    assert_eq!(&*imported.reader(), &*vec.reader());
+    assert!(RWVec::<(u32, String)>::import_compressed(&exported[..10]).is_err());
+}
+
+#[cfg(feature = "flatbuffers")]
+#[test]
+fn flatbuffer_round_trip() {
+    let vec = RWVec::<u32>::new();
+    vec.extend_from_slice(&[1, 2, 3]);
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let exported    = vec.export_flatbuffer(&mut builder);
+    builder.finish_minimal(exported);
+
+    let vector   = unsafe { flatbuffers::root_unchecked::<flatbuffers::Vector<u32>>(builder.finished_data()) };
+    let imported = RWVec::import_flatbuffer(vector);
+    assert_eq!(&*imported.reader(), &[1, 2, 3][..]);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn arrow_export() {
+    use arrow::array::Array;
+
+    let vec = RWVec::<f64>::new();
+    vec.extend_from_slice(&[1.0, 2.5]);
+
+    let array = vec.to_arrow::<arrow::datatypes::Float64Type>();
+    assert_eq!(array.values(), &[1.0, 2.5]);
+    assert_eq!(array.null_count(), 0);
+
+    let batch = vec.to_record_batch::<arrow::datatypes::Float64Type>("x").unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.schema().field(0).name(), "x");
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_are_published_per_vec() {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicU64;
+
+    //keeps every counter and gauge in an atomic, keyed by name and vec label
+    #[derive(Default)]
+    struct Recorded(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+    impl Recorded {
+        fn slot(&self, key : &metrics::Key) -> Arc<AtomicU64> {
+            let vec = key.labels().find(|label| label.key() == "vec").map_or("", |label| label.value()).to_string();
+            self.0.lock().unwrap().entry(format!("{}/{}", key.name(), vec)).or_default().clone()
+        }
+
+        fn get(&self, name : &str) -> u64 {
+            self.0.lock().unwrap()[name].load(Ordering::SeqCst)
+        }
+    }
+
+    impl metrics::Recorder for Recorded {
+        fn describe_counter(&self, _ : metrics::KeyName, _ : Option<metrics::Unit>, _ : metrics::SharedString) { }
+        fn describe_gauge(&self, _ : metrics::KeyName, _ : Option<metrics::Unit>, _ : metrics::SharedString) { }
+        fn describe_histogram(&self, _ : metrics::KeyName, _ : Option<metrics::Unit>, _ : metrics::SharedString) { }
+
+        fn register_counter(&self, key : &metrics::Key, _ : &metrics::Metadata) -> metrics::Counter {
+            metrics::Counter::from_arc(self.slot(key))
+        }
+
+        fn register_gauge(&self, key : &metrics::Key, _ : &metrics::Metadata) -> metrics::Gauge {
+            metrics::Gauge::from_arc(self.slot(key))
+        }
+
+        fn register_histogram(&self, _ : &metrics::Key, _ : &metrics::Metadata) -> metrics::Histogram {
+            metrics::Histogram::noop()
+        }
+    }
+
+    let recorded = Recorded::default();
+    metrics::with_local_recorder(&recorded, || {
+        let named = RWVec::<u32>::builder().name("queue").capacity(1).build();
+        named.extend_from_slice(&[1, 2, 3]);
+        named.publish_metrics();
+
+        //unnamed vecs are left out
+        RWVec::<u32>::new().publish_metrics();
+    });
+
+    assert_eq!(f64::from_bits(recorded.get("rwvec_len/queue")), 3.0);
+    assert_eq!(recorded.get("rwvec_pushes_total/queue"), 3);
+    assert_eq!(recorded.get("rwvec_realloc_pushes_total/queue"), 1);
+    assert_eq!(recorded.0.lock().unwrap().len(), 5);
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn slow_locks_get_logged() {
+    //the only logger the tests install, it keeps every warning
+    struct Warnings(Mutex<std::vec::Vec<String>>);
+
+    impl log::Log for Warnings {
+        fn enabled(&self, _ : &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record : &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) { }
+    }
+
+    static WARNINGS : Warnings = Warnings(Mutex::new(std::vec::Vec::new()));
+    log::set_logger(&WARNINGS).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let vec = RWVec::<u32>::builder().name("slow").slow_lock_warnings(Duration::from_millis(5), Duration::from_millis(5)).build();
+    std::thread::scope(|scope| {
+        let writer = vec.writer();
+        scope.spawn(|| drop(vec.reader()));
+        std::thread::sleep(Duration::from_millis(20));
+        drop(writer);
+    });
+
+    let warnings = WARNINGS.0.lock().unwrap();
+    assert!(warnings.iter().any(|w| w.starts_with("RWVec 'slow': write guard held for")), "{:?}", warnings);
+    assert!(warnings.iter().any(|w| w.starts_with("RWVec 'slow': waited") && w.contains("for the read lock")), "{:?}", warnings);
+}
+
+#[test]
+fn custom_backends_under_contention() {
+    for &(yields, park) in &[(0, true), (4, true), (4, false)] {
+        let backoff = Backoff { rounds : 2, max_pause : 8, yields : yields, park : park };
+        hammer(&RWVec::builder().capacity(1).lock_backend(LockBackend::Custom(backoff)).build());
+    }
+}
+
+#[test]
+fn push_lock_is_exclusive() {
+    struct Counted(UnsafeCell<usize>);
+    unsafe impl Sync for Counted { }
+
+    let lock  = PushLock::new();
+    let count = Counted(UnsafeCell::new(0));
+
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for _ in 0..10_000 {
+                    lock.acquire(LockBackend::Blocking);
+                    unsafe {
+                        //a plain read and write, torn if two threads were in here at once
+                        let seen = ptr::read_volatile(count.0.get());
+                        ptr::write_volatile(count.0.get(), seen + 1);
+                        lock.unlock();
+                    }
+                }
+            });
+        }
+    });
+
+    assert_eq!(count.0.into_inner(), 80_000);
+    assert!(lock.try_lock());
+}
+
+#[test]
+fn partition_moves_without_growing_anything() {
+    let vec = RWVec::<String>::with_capacity(16);
+    for i in 0..10 {
+        vec.push(i.to_string());
+    }
+
+    //nothing was grown along the way, so nothing was freed without being wiped
+    let (even, odd) = vec.partition(|t| t.parse::<u32>().unwrap() % 2 == 0);
+    assert_eq!((even.len(), odd.len()), (5, 5));
+    assert_eq!((even.capacity(), odd.capacity()), (10, 10));
+    assert_eq!(unsafe { vec.vec().capacity() }, 16);
+    assert_eq!(vec.reader().len(), 0);
+}
+
+#[test]
+fn merge_into_a_bounded_vec_moves_all_or_nothing() {
+    let rejecting = RWVec::<u32>::builder().bounded(3, OverflowPolicy::Reject).build();
+    let from      = RWVec::<u32>::new();
+    rejecting.extend_from_slice(&[1, 2]);
+    from.extend_from_slice(&[3, 4]);
+
+    //too many to fit, so both are left as they were
+    assert_eq!(rejecting.merge(&from), Err(RwVecError::Full));
+    assert_eq!(&*rejecting.reader(), &[1, 2][..]);
+    assert_eq!(&*from.reader(), &[3, 4][..]);
+
+    let oldest = RWVec::<u32>::builder().bounded(3, OverflowPolicy::DropOldest).build();
+    oldest.extend_from_slice(&[1, 2]);
+    oldest.merge(&from).unwrap();
+    assert_eq!(&*oldest.reader(), &[2, 3, 4][..]);
+    assert_eq!(from.reader().len(), 0);
+
+    let budgeted = RWVec::<String>::builder().byte_budget(4, |s| s.len()).build();
+    let strings  = RWVec::<String>::new();
+    budgeted.push("aa".to_string());
+    strings.push("bb".to_string());
+    strings.push("c".to_string());
+    budgeted.merge(&strings).unwrap();
+    assert_eq!(&*budgeted.reader(), &["bb".to_string(), "c".to_string()][..]);
+}
+
+#[test]
+fn shrinks_after_a_drain_with_no_pushes() {
+    let now = RWVec::<u32>::builder().capacity(64).shrink_when_below(0.25, Duration::from_millis(0)).build();
+    now.extend_from_slice(&[0; 40]);
+    now.drain(2..);
+    assert!(unsafe { now.vec().capacity() } < 64);
+
+    //the drain starts the clock, the next exclusive section once it has run out shrinks
+    let later = RWVec::<u32>::builder().capacity(64).shrink_when_below(0.25, Duration::from_millis(20)).build();
+    later.extend_from_slice(&[0; 40]);
+    later.drain(2..);
+    assert_eq!(unsafe { later.vec().capacity() }, 64);
+
+    std::thread::sleep(Duration::from_millis(30));
+    later.drain(..0);
+    assert!(unsafe { later.vec().capacity() } < 64);
+    assert_eq!(later.reader().len(), 2);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn poll_reserve_never_waits_for_the_push_lock() {
+    struct Wakes(AtomicUsize);
+
+    impl std::task::Wake for Wakes {
+        fn wake(self : Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let vec    = RWVec::<u32>::builder().bounded(1, OverflowPolicy::Reject).build();
+    let wakes  = Arc::new(Wakes(AtomicUsize::new(0)));
+    let waker  = Waker::from(wakes.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    //with the lock held elsewhere it asks to be polled again right away
+    let exclusive = vec.exclusive();
+    assert!(vec.poll_reserve(&mut cx).is_pending());
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    drop(exclusive);
+
+    assert!(vec.poll_reserve(&mut cx).is_ready());
+}
+
+#[test]
+fn snapshot_together_turns_away_the_same_vec_twice() {
+    let vec = RWVec::<u32>::new();
+    vec.push(1);
+
+    let twice = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(snapshot_together((&*vec, &*vec)))));
+    assert!(twice.is_err());
+
+    //nothing was left locked
+    vec.push(2);
+    assert_eq!(vec.writer().len(), 2);
+}
 
 // #[test]
 // fn basic() {